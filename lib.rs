@@ -43,18 +43,105 @@ impl Environment for CustomEnvironment {
     type ChainExtension = FetchRandom;
 }
 
+// the constructor enum generated by ink holds the whole `LotteryConfig`
+#[allow(clippy::large_enum_variant)]
 #[ink::contract(env = crate::CustomEnvironment)]
 mod lottery {
     pub type Result<T> = core::result::Result<T, Error>;
-    use ink_storage::{traits::SpreadAllocate, Mapping};
+    /// Picked or drawn numbers, sorted for unordered games, followed by the bonus number for
+    /// games with a bonus ball.
+    pub type Ticket = Vec<u8>;
+    use ink_prelude::{vec, vec::Vec};
+    use ink_primitives::KeyPtr;
+    use ink_storage::{
+        traits::{PackedLayout, SpreadAllocate, SpreadLayout},
+        Mapping,
+    };
 
     /// Emitted whenever a new ticket is being registered.
     #[ink(event)]
     pub struct RegisterTicket {
         #[ink(topic)]
-        ticket: [u8; 3],
+        ticket: Ticket,
         #[ink(topic)]
         from: AccountId,
+        shares: u32,
+    }
+
+    /// Emitted whenever a holder cancels the shares of a ticket bought since the last drawing.
+    #[ink(event)]
+    pub struct TicketCancelled {
+        #[ink(topic)]
+        ticket: Ticket,
+        #[ink(topic)]
+        from: AccountId,
+        shares: u32,
+        refund: Balance,
+    }
+
+    /// Emitted whenever a new box ticket is being registered, `ticket` is sorted.
+    #[ink(event)]
+    pub struct RegisterBoxTicket {
+        #[ink(topic)]
+        ticket: Ticket,
+        #[ink(topic)]
+        from: AccountId,
+    }
+
+    /// Emitted whenever a prize of a partial-match tier is being claimed.
+    #[ink(event)]
+    pub struct PrizeClaimed {
+        #[ink(topic)]
+        draw: u32,
+        ticket: Ticket,
+        #[ink(topic)]
+        to: AccountId,
+        amount: Balance,
+    }
+
+    /// Emitted whenever the owner withdraws from the treasury.
+    #[ink(event)]
+    pub struct TreasuryWithdrawn {
+        #[ink(topic)]
+        to: AccountId,
+        amount: Balance,
+    }
+
+    /// Emitted whenever a sponsor adds to the jackpot of a round.
+    #[ink(event)]
+    pub struct JackpotSeeded {
+        #[ink(topic)]
+        sponsor: AccountId,
+        #[ink(topic)]
+        round: u32,
+        amount: Balance,
+        memo: Vec<u8>,
+    }
+
+    /// Emitted whenever a liquidity provider adds to the bankroll.
+    #[ink(event)]
+    pub struct LiquidityDeposited {
+        #[ink(topic)]
+        provider: AccountId,
+        amount: Balance,
+        shares: Balance,
+    }
+
+    /// Emitted whenever a liquidity provider takes out of the bankroll.
+    #[ink(event)]
+    pub struct LiquidityWithdrawn {
+        #[ink(topic)]
+        provider: AccountId,
+        amount: Balance,
+        shares: Balance,
+    }
+
+    /// Emitted whenever the reserve tops up a jackpot below the minimum.
+    #[ink(event)]
+    pub struct ReserveUsed {
+        #[ink(topic)]
+        round: u32,
+        amount: Balance,
     }
 
     impl Default for Lottery {
@@ -63,20 +150,714 @@ mod lottery {
         }
     }
 
+    /// Prize paid to every winner of a tier.
+    #[derive(
+        Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode, SpreadLayout, PackedLayout,
+    )]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout)
+    )]
+    pub enum Prize {
+        /// Share of the jackpot in basis points, split between all winners of the tier.
+        Share(u16),
+        /// Fixed amount per winner, capped by the jackpot.
+        Fixed(Balance),
+    }
+
+    /// Prize tier for tickets with `matches` numbers right, in position for ordered games.
+    ///
+    /// In games with a bonus ball `bonus` tells if the bonus number has to be right or wrong.
+    #[derive(
+        Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode, SpreadLayout, PackedLayout,
+    )]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout)
+    )]
+    pub struct PrizeTier {
+        pub matches: u8,
+        pub bonus: bool,
+        pub prize: Prize,
+    }
+
+    impl Default for PrizeTier {
+        fn default() -> Self {
+            Self {
+                matches: 0,
+                bonus: false,
+                prize: Prize::Fixed(0),
+            }
+        }
+    }
+
+    /// Shape of the tickets, `numbers` picks between `min` and `max`.
+    #[derive(
+        Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode, SpreadLayout, PackedLayout,
+    )]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout)
+    )]
+    pub struct TicketShape {
+        pub numbers: u8,
+        pub min: u8,
+        pub max: u8,
+        /// The sorting of the picks matters.
+        pub ordered: bool,
+        /// The same number may be picked more than once.
+        pub duplicates: bool,
+    }
+
+    impl Default for TicketShape {
+        fn default() -> Self {
+            Self {
+                numbers: 3,
+                min: 0,
+                max: 255,
+                ordered: true,
+                duplicates: true,
+            }
+        }
+    }
+
+    /// Secondary number between `min` and `max`, drawn next to the main numbers.
+    #[derive(
+        Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode, SpreadLayout, PackedLayout,
+    )]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout)
+    )]
+    pub struct BonusBall {
+        pub min: u8,
+        pub max: u8,
+    }
+
+    /// Box bets win if the drawn numbers match in any order.
+    #[derive(
+        Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode, SpreadLayout, PackedLayout,
+    )]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout)
+    )]
+    pub struct BoxBet {
+        pub price: Balance,
+        /// Prize per winning box ticket, paid out of the jackpot.
+        pub prize: Prize,
+    }
+
+    /// Payouts of the fixed-odds mode and the limits keeping them covered by the bankroll.
+    #[derive(
+        Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode, SpreadLayout, PackedLayout,
+    )]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout)
+    )]
+    pub struct FixedOdds {
+        /// Multiple of the stake paid for every share of the winning ticket.
+        pub multiplier: u32,
+        /// Highest payout a single ticket of a round may win.
+        pub max_ticket_exposure: Balance,
+        /// Highest sum of the payouts of all bets of a round.
+        pub max_round_exposure: Balance,
+    }
+
+    /// Where the sales beyond the jackpot cap go.
+    #[derive(
+        Debug,
+        Default,
+        Clone,
+        Copy,
+        PartialEq,
+        Eq,
+        scale::Encode,
+        scale::Decode,
+        SpreadLayout,
+        PackedLayout,
+    )]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout)
+    )]
+    pub enum Overflow {
+        /// Added to the prize of the best prize tier with winners in the next drawing.
+        LowerTiers,
+        /// Kept in a secondary pot which starts the jackpot of the next round.
+        #[default]
+        SecondaryPot,
+    }
+
+    /// What happens to value sent beyond the price of a purchase.
+    #[derive(
+        Debug,
+        Default,
+        Clone,
+        Copy,
+        PartialEq,
+        Eq,
+        scale::Encode,
+        scale::Decode,
+        SpreadLayout,
+        PackedLayout,
+    )]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout)
+    )]
+    pub enum Overpayment {
+        /// Sent back in the same call.
+        #[default]
+        Refund,
+        /// Added to the balance of the buyer which later purchases draw from.
+        Credit,
+    }
+
+    /// Game played by the lottery.
+    #[derive(
+        Debug,
+        Default,
+        Clone,
+        Copy,
+        PartialEq,
+        Eq,
+        scale::Encode,
+        scale::Decode,
+        SpreadLayout,
+        PackedLayout,
+    )]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout)
+    )]
+    pub enum GameMode {
+        /// Tickets win by matching the drawn numbers.
+        #[default]
+        Numbers,
+        /// Every drawing picks one of the entries sold, weighted by their shares.
+        Raffle,
+        /// Winning tickets are paid a fixed multiple of their stake from the bankroll, every
+        /// drawing ends the round.
+        FixedOdds,
+        /// Players deposit a withdrawable principal and every drawing picks one of them,
+        /// weighted by principal and time, to win the sponsored jackpot.
+        NoLoss,
+    }
+
+    /// Settings of the game, fixed at construction.
+    #[derive(Debug, Clone, Default, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub struct LotteryConfig {
+        pub mode: GameMode,
+        pub ticket: TicketShape,
+        /// Bonus number picked after the main numbers, disabled if `None`.
+        pub bonus: Option<BonusBall>,
+        /// Partial-match tiers, the jackpot itself is always won by matching all numbers
+        /// including the bonus number.
+        pub prize_tiers: Vec<PrizeTier>,
+        /// Unordered bets for ordered games, disabled if `None`.
+        pub box_bet: Option<BoxBet>,
+        /// Part of every sale going to the treasury in basis points.
+        pub house_fee: u16,
+        /// Part of every sale going to the reserve in basis points.
+        pub reserve_share: u16,
+        /// Jackpot guaranteed to the winners as far as the reserve allows.
+        pub min_jackpot: Balance,
+        /// Highest jackpot, the sales beyond it go to `overflow`, no cap if `None`.
+        pub jackpot_cap: Option<Balance>,
+        pub overflow: Overflow,
+        /// Number of drawings in a round after which the jackpot goes to the tickets with the
+        /// most numbers right if nobody has all of them, never if `None`.
+        pub must_be_won_after: Option<u32>,
+        /// Jackpot shares in basis points of the extra winning tickets drawn after the main
+        /// one, the main winning ticket gets the rest of the jackpot.
+        pub runner_ups: Vec<u16>,
+        /// Part of every sale going to the second-chance pot in basis points.
+        pub second_chance_share: u16,
+        /// Settings of the fixed-odds mode, required in that mode only.
+        pub fixed_odds: Option<FixedOdds>,
+        /// Part of the price kept in the treasury when a ticket is cancelled in basis points.
+        pub cancellation_fee: u16,
+        pub overpayment: Overpayment,
+    }
+
+    /// Result of a prize tier in a single drawing.
+    #[derive(
+        Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode, SpreadLayout, PackedLayout,
+    )]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout)
+    )]
+    pub struct TierResult {
+        pub matches: u8,
+        pub bonus: bool,
+        pub winners: u32,
+        pub prize_per_winner: Balance,
+    }
+
+    /// Kind of bet a ticket is registered for.
+    #[derive(
+        Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode, SpreadLayout, PackedLayout,
+    )]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout)
+    )]
+    pub enum BetKind {
+        Ticket,
+        Box,
+        /// Raffle entries, held as shares of an empty ticket.
+        Raffle,
+    }
+
+    /// Ticket entered for `holder` at the start of every round until no round is left.
+    #[derive(
+        Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode, SpreadLayout, PackedLayout,
+    )]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout)
+    )]
+    pub struct Subscription {
+        pub id: u32,
+        pub holder: AccountId,
+        pub ticket: Ticket,
+        /// prepaid rounds not entered yet
+        pub rounds_left: u32,
+    }
+
+    /// Principal of a player of the no-loss mode with its weight in the drawing `period`.
+    #[derive(
+        Debug,
+        Default,
+        Clone,
+        Copy,
+        PartialEq,
+        Eq,
+        scale::Encode,
+        scale::Decode,
+        SpreadLayout,
+        PackedLayout,
+    )]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout)
+    )]
+    struct Depositor {
+        index: u32,
+        principal: Balance,
+        /// principal times blocks held up to block `updated`
+        weight: Balance,
+        period: u32,
+        updated: BlockNumber,
+    }
+
+    /// Entry of a raffle covering the positions from `start` on, one per share.
+    #[derive(
+        Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode, SpreadLayout, PackedLayout,
+    )]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout)
+    )]
+    struct RaffleEntry {
+        holder: AccountId,
+        #[codec(compact)]
+        start: u32,
+    }
+
+    /// Winner of a raffle drawing.
+    #[derive(
+        Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode, SpreadLayout, PackedLayout,
+    )]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout)
+    )]
+    pub struct RaffleWinner {
+        /// drawn position out of all shares of the round, or out of the players of the round
+        /// for the second chance
+        pub position: u32,
+        pub account: AccountId,
+        pub prize: Balance,
+    }
+
+    /// Shares an account bought of a ticket in the same drawing.
+    #[derive(
+        Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode, SpreadLayout, PackedLayout,
+    )]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout)
+    )]
+    struct HolderEntry {
+        /// first drawing the shares take part in
+        #[codec(compact)]
+        draw: u32,
+        #[codec(compact)]
+        shares: u32,
+        /// purchases the shares were bought with
+        #[codec(compact)]
+        purchases: u32,
+    }
+
+    /// Number of accounts holding a ticket and the shares they hold together.
+    #[derive(
+        Debug,
+        Default,
+        Clone,
+        Copy,
+        PartialEq,
+        Eq,
+        scale::Encode,
+        scale::Decode,
+        SpreadLayout,
+        PackedLayout,
+    )]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout)
+    )]
+    struct TicketTotals {
+        #[codec(compact)]
+        holders: u32,
+        #[codec(compact)]
+        shares: u32,
+    }
+
+    /// Prize per share which is paid to every holder of a winning ticket, a batch of holders at a
+    /// time.
+    #[derive(
+        Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode, SpreadLayout, PackedLayout,
+    )]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout)
+    )]
+    struct Payout {
+        kind: BetKind,
+        ticket: Ticket,
+        round: u32,
+        /// drawing the prize was won in, later shares of the holders are not paid
+        draw: u32,
+        next_holder: u32,
+        holders: u32,
+        prize: Balance,
+        /// single winner paid `prize` once instead of the holders of `ticket`
+        winner: Option<AccountId>,
+    }
+
+    /// Outcome of a drawing, kept as round history.
+    #[derive(
+        Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode, SpreadLayout, PackedLayout,
+    )]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout)
+    )]
+    pub struct DrawResult {
+        pub round: u32,
+        pub block: BlockNumber,
+        /// Distinct winning tickets, the main one first.
+        pub winning_tickets: Vec<WinningTicket>,
+        pub tiers: Vec<TierResult>,
+        pub box_winners: u32,
+        pub box_prize_per_winner: Balance,
+        /// Tickets sharing the jackpot which had to be won, `bonus` is not taken into account.
+        pub closest_matches: Option<TierResult>,
+        pub raffle_winner: Option<RaffleWinner>,
+        /// Player of the round winning the second-chance pot when nobody won the jackpot.
+        pub second_chance_winner: Option<RaffleWinner>,
+    }
+
+    /// Winning ticket of a drawing with the part of the jackpot it won.
+    #[derive(
+        Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode, SpreadLayout, PackedLayout,
+    )]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout)
+    )]
+    pub struct WinningTicket {
+        pub ticket: Ticket,
+        pub winners: u32,
+        pub prize_per_winner: Balance,
+    }
+
+    /// Ticket an account holds with its shares.
+    #[derive(Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub struct AccountTicket {
+        pub kind: BetKind,
+        pub round: u32,
+        pub ticket: Ticket,
+        pub shares: u32,
+    }
+
+    /// Phase of the actual round.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum Phase {
+        /// tickets are sold until the next drawing
+        Open,
+        /// the next drawing is due, the next purchase draws
+        DrawDue,
+    }
+
+    /// Everything needed to show the lottery at once.
+    #[derive(Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub struct LotteryState {
+        pub round: u32,
+        pub phase: Phase,
+        pub config: LotteryConfig,
+        pub jackpot: Balance,
+        pub last_jackpot: Balance,
+        pub last_pot_per_ticket: Balance,
+        pub treasury: Balance,
+        pub reserve: Balance,
+        pub secondary_pot: Balance,
+        pub second_chance_pot: Balance,
+        pub bankroll: Balance,
+        pub round_exposure: Balance,
+        pub total_principal: Balance,
+        pub unclaimed_prizes: Balance,
+        pub pending_payouts: u32,
+        /// balance of the contract
+        pub balance: Balance,
+        pub draw_count: u32,
+        pub last_drawing: BlockNumber,
+        pub next_drawing: BlockNumber,
+        /// failed payouts and tier prizes the caller can still withdraw or claim
+        pub claimable: Balance,
+        pub last_result: Option<DrawResult>,
+    }
+
+    /// Funds of the contract by what they are kept for.
+    #[derive(Debug, Default, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub struct Ledger {
+        pub jackpot: Balance,
+        pub treasury: Balance,
+        /// prizes won but not claimed or paid out yet
+        pub claimable: Balance,
+        pub reserve: Balance,
+        /// jackpots seeded for future rounds
+        pub seeded: Balance,
+        /// sales beyond the jackpot cap
+        pub secondary_pot: Balance,
+        pub second_chance_pot: Balance,
+        /// funds paying the fixed-odds winners
+        pub bankroll: Balance,
+        /// deposits of the no-loss players
+        pub principal: Balance,
+        /// rounds prepaid by subscriptions and not entered yet
+        pub subscriptions: Balance,
+        /// balances of the players for later purchases
+        pub balances: Balance,
+        /// balance beyond all of the above, e.g. rounding dust or value sent directly
+        pub unallocated: Balance,
+    }
+
+    /// Outcome of comparing the funds owed with the balance of the contract.
+    #[derive(Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub struct Solvency {
+        pub ledger: Ledger,
+        /// sum of all ledgers except the unallocated funds
+        pub liabilities: Balance,
+        pub balance: Balance,
+        pub solvent: bool,
+    }
+
+    /// Pool of the liquidity providers backing the bankroll.
+    #[derive(Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub struct Pool {
+        pub bankroll: Balance,
+        pub total_shares: Balance,
+        /// value of `SHARE_PRICE_UNIT` shares
+        pub share_price: Balance,
+        /// payouts of all fixed-odds bets of the actual round if they won
+        pub exposure: Balance,
+        /// part of the bankroll not covering the exposure
+        pub free: Balance,
+    }
+
+    /// Sales of a round over ticket and box bets.
+    #[derive(
+        Debug,
+        Default,
+        Clone,
+        PartialEq,
+        Eq,
+        scale::Encode,
+        scale::Decode,
+        SpreadLayout,
+        PackedLayout,
+    )]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink_storage::traits::StorageLayout)
+    )]
+    pub struct RoundStats {
+        /// number of purchases
+        pub tickets_sold: u32,
+        pub shares_sold: u32,
+        pub unique_players: u32,
+        pub distinct_combinations: u32,
+        /// average shares of every distinct combination, rounded down
+        pub shares_per_ticket: u32,
+        pub total_collected: Balance,
+        /// drawings held in the round
+        pub draws: u32,
+    }
+
+    /// Fields added after the first release, kept in a single cell behind the fields of the
+    /// first release. A contract upgraded from the first release reads the default with
+    /// `version` 0 until `migrate_legacy_state` fills it.
+    #[derive(Debug, Default, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    struct State {
+        version: u32,
+        config: LotteryConfig,
+        owner: AccountId,
+        round: u32,
+        draw_count: u32,
+        winner_tickets: Vec<Ticket>,
+        unclaimed_prizes: Balance,
+        /// queue of the payouts which are not done yet
+        first_payout: u32,
+        payout_count: u32,
+        pending_payouts: u32,
+        /// fees for the operation of the lottery, kept apart from the jackpot and the prizes
+        treasury: Balance,
+        /// funds kept back for future jackpots
+        reserve: Balance,
+        /// sales beyond the jackpot cap
+        secondary_pot: Balance,
+        /// share of the sales paid to a player when nobody wins the jackpot
+        second_chance_pot: Balance,
+        /// funds paying the fixed-odds winners, the stakes go into it
+        bankroll: Balance,
+        /// payouts of all fixed-odds bets of the actual round if they won
+        round_exposure: Balance,
+        lp_total_shares: Balance,
+        depositor_count: u32,
+        total_principal: Balance,
+        subscription_count: u32,
+        active_subscription_count: u32,
+        /// active subscriptions before this index are entered for the actual round
+        subscriptions_entered: u32,
+        subscription_funds: Balance,
+        balances_total: Balance,
+        last_raffle_winner: Option<AccountId>,
+        seeded_total: Balance,
+    }
+
+    impl SpreadLayout for State {
+        const FOOTPRINT: u64 = 1;
+
+        fn pull_spread(ptr: &mut KeyPtr) -> Self {
+            ink_env::get_contract_storage(ptr.advance_by(1))
+                .ok()
+                .flatten()
+                .unwrap_or_default()
+        }
+
+        fn push_spread(&self, ptr: &mut KeyPtr) {
+            ink_env::set_contract_storage_return_size(ptr.advance_by(1), self);
+        }
+
+        fn clear_spread(&self, ptr: &mut KeyPtr) {
+            ink_env::clear_contract_storage(ptr.advance_by(1));
+        }
+    }
+
+    impl SpreadAllocate for State {
+        fn allocate_spread(ptr: &mut KeyPtr) -> Self {
+            ptr.advance_by(1);
+            Self::default()
+        }
+    }
+
+    #[cfg(feature = "std")]
+    impl ink_storage::traits::StorageLayout for State {
+        fn layout(key_ptr: &mut KeyPtr) -> ink_metadata::layout::Layout {
+            ink_metadata::layout::Layout::Cell(ink_metadata::layout::CellLayout::new::<Self>(
+                ink_metadata::layout::LayoutKey::from(key_ptr.advance_by(1)),
+            ))
+        }
+    }
+
     /// Defines the storage of your contract.
     /// Add new fields to the below struct in order
     /// to add new static storage fields to your contract.
     #[ink(storage)]
     #[derive(SpreadAllocate)]
     pub struct Lottery {
-        ticket_and_address: Mapping<([u8; 3], u8), [AccountId; 8]>,
-        round: u8,
+        /// holders of a ticket per round before the per-holder entries, the fields up to
+        /// `legacy_default_address` keep the layout of the first release
+        legacy_ticket_and_address: Mapping<([u8; 3], u8), [AccountId; 8]>,
+        legacy_round: u8,
         last_drawing: BlockNumber,
         jackpot: Balance,
-        winner_ticket: [u8; 3],
+        legacy_winner_ticket: [u8; 3],
         last_jackpot: Balance,
         last_pot_per_ticket: Balance,
-        default_address: [AccountId; 8],
+        legacy_default_address: [AccountId; 8],
+        state: State,
+        /// accounts holding a ticket per round by holder index, box tickets by their sorted numbers
+        ticket_holders: Mapping<(BetKind, Ticket, u32, u32), AccountId>,
+        ticket_totals: Mapping<(BetKind, Ticket, u32), TicketTotals>,
+        /// shares an account holds of a ticket per round, grouped by the drawing they were bought for
+        holder_entries: Mapping<(BetKind, Ticket, u32, AccountId), Vec<HolderEntry>>,
+        /// holder index of an account in `ticket_holders`
+        holder_positions: Mapping<(BetKind, Ticket, u32, AccountId), u32>,
+        /// tickets of an account over all rounds in the order they were first bought, a
+        /// cancelled ticket is replaced by the last one
+        account_tickets: Mapping<(AccountId, u32), (BetKind, u32, Ticket)>,
+        account_ticket_count: Mapping<AccountId, u32>,
+        /// positions in `account_tickets` of the tickets of an account per round
+        account_round_tickets: Mapping<(AccountId, u32, u32), u32>,
+        account_round_ticket_count: Mapping<(AccountId, u32), u32>,
+        round_stats: Mapping<u32, RoundStats>,
+        /// number of tickets per round sharing the numbers picked by a mask
+        match_counts: Mapping<(u16, Ticket, u32), u32>,
+        claimed: Mapping<(u32, Ticket, AccountId), ()>,
+        draw_results: Mapping<u32, DrawResult>,
+        payouts: Mapping<u32, Payout>,
+        /// payouts which failed to transfer, still part of the unclaimed prizes
+        credits: Mapping<AccountId, Balance>,
+        /// accounts holding tickets per round in the order of their first purchase, counted by
+        /// the unique players of the round
+        round_players: Mapping<(u32, u32), AccountId>,
+        round_player_positions: Mapping<(u32, AccountId), u32>,
+        /// shares of the liquidity providers in the bankroll
+        lp_shares: Mapping<AccountId, Balance>,
+        /// block of the last deposit of every liquidity provider
+        lp_deposit_blocks: Mapping<AccountId, BlockNumber>,
+        /// players of the no-loss mode in the order of their first deposit
+        depositors: Mapping<AccountId, Depositor>,
+        depositor_accounts: Mapping<u32, AccountId>,
+        /// subscriptions by id, the active ones are entered in batches once a round starts
+        subscriptions: Mapping<u32, Subscription>,
+        /// ids of the active subscriptions by index
+        active_subscriptions: Mapping<u32, u32>,
+        account_subscriptions: Mapping<AccountId, Vec<u32>>,
+        /// deposits and overpayments of the players which later purchases draw from
+        balances: Mapping<AccountId, Balance>,
+        /// accounts whose prizes are credited to their balance instead of sent
+        prizes_to_balance: Mapping<AccountId, ()>,
+        /// entries of the raffle per round in the order they were bought
+        raffle_entries: Mapping<(u32, u32), RaffleEntry>,
+        raffle_entry_count: Mapping<u32, u32>,
+        /// jackpots seeded for future rounds
+        seeded_jackpots: Mapping<u32, Balance>,
+        sponsor_totals: Mapping<AccountId, Balance>,
     }
 
     /// Errors that can occur upon calling this contract.
@@ -85,487 +866,4793 @@ mod lottery {
     pub enum Error {
         TicketAlreadyExists,
         TicketCosts,
+        DrawNotFound,
+        NoPrize,
+        PrizeAlreadyClaimed,
+        TransferFailed,
+        BoxBetsDisabled,
+        InvalidTicket,
+        InvalidQuantity,
+        NotOwner,
+        InsufficientTreasury,
+        NoCredit,
+        NoValue,
+        InvalidRound,
+        WrongMode,
+        ExposureLimit,
+        InsufficientShares,
+        WithdrawalLocked,
+        InsufficientPrincipal,
+        TooManyDepositors,
+        TooManySubscriptions,
+        SubscriptionNotFound,
+        NothingToCancel,
+        CancellationClosed,
+        InsufficientBalance,
+        AlreadyMigrated,
+        MigrationPending,
     }
 
     const BET_PRICE: Balance = 1_000_000;
+    /// version of `State` written by the constructors and the migration
+    const STATE_VERSION: u32 = 1;
+    /// Owner of a contract upgraded from the first release, which had none, and the only
+    /// account allowed to migrate it. Set it before building the code the contract is upgraded
+    /// to, the zero account can't sign so the migration stays closed until then.
+    const LEGACY_OWNER: [u8; 32] = [0; 32];
     const BLOCKS_PER_ROUND: u32 = 1000;
+    const LP_COOLDOWN: u32 = BLOCKS_PER_ROUND;
+    const SHARE_PRICE_UNIT: Balance = 1_000_000;
+    const MAX_DEPOSITORS: u32 = 500;
+    /// smallest principal a depositor may hold, so the depositor slots can't be filled cheaply
+    const MIN_PRINCIPAL: Balance = BET_PRICE;
+    const MAX_ACCOUNT_SUBSCRIPTIONS: usize = 10;
+    const CANCELLATION_CUTOFF: u32 = 100;
+    const SECOND_CHANCE_TAG: &[u8] = b"second-chance";
+    const MAX_NUMBERS: u8 = 8;
+    /// most main numbers with `must_be_won_after`, every combination of them is counted per
+    /// purchase
+    const MAX_MUST_BE_WON_NUMBERS: u8 = 6;
+    const PAYOUTS_PER_DRAW: u32 = 8;
+    const MAX_PAYOUTS_PER_CALL: u32 = 100;
+    const SUBSCRIPTIONS_PER_DRAW: u32 = 4;
+    const MAX_SUBSCRIPTIONS_PER_CALL: u32 = 20;
+    const MAX_PAGE_SIZE: u32 = 100;
+    const BASIS_POINTS: Balance = 10_000;
+    const MAX_WINNING_TICKETS: usize = 8;
 
     impl Lottery {
         #[ink(constructor)]
         pub fn new() -> Self {
-            ink_lang::utils::initialize_contract(Self::new_init)
+            Self::with_config(LotteryConfig::default())
+        }
+
+        /// Creates a lottery with the given prize tiers and settings.
+        #[ink(constructor)]
+        pub fn with_config(config: LotteryConfig) -> Self {
+            let shape = config.ticket;
+            assert!(
+                shape.numbers > 0
+                    && shape.numbers <= MAX_NUMBERS
+                    && shape.min <= shape.max
+                    && (shape.duplicates || shape.max - shape.min >= shape.numbers - 1),
+                "invalid ticket shape!"
+            );
+            if let Some(bonus) = config.bonus {
+                assert!(bonus.min <= bonus.max, "invalid bonus ball!");
+            }
+            for tier in config.prize_tiers.iter() {
+                let main_numbers_only = config.bonus.is_some() && !tier.bonus;
+                assert!(
+                    tier.matches > 0
+                        && (tier.matches < shape.numbers
+                            || (tier.matches == shape.numbers && main_numbers_only))
+                        && (config.bonus.is_some() || !tier.bonus)
+                        && (shape.ordered || !shape.duplicates),
+                    "invalid prize tier!"
+                );
+            }
+            if let Some(box_bet) = config.box_bet {
+                assert!(box_bet.price > 0 && shape.ordered, "invalid box bet!");
+            }
+            assert!(
+                Balance::from(config.house_fee) <= BASIS_POINTS,
+                "invalid house fee!"
+            );
+            assert!(
+                Balance::from(config.house_fee) + Balance::from(config.reserve_share)
+                    <= BASIS_POINTS,
+                "invalid reserve share!"
+            );
+            assert!(
+                Balance::from(config.cancellation_fee) <= BASIS_POINTS,
+                "invalid cancellation fee!"
+            );
+            assert!(
+                Balance::from(config.house_fee)
+                    + Balance::from(config.reserve_share)
+                    + Balance::from(config.second_chance_share)
+                    <= BASIS_POINTS
+                    && (config.second_chance_share == 0 || config.mode == GameMode::Numbers),
+                "invalid second chance share!"
+            );
+            assert!(
+                config.jackpot_cap != Some(0)
+                    && (config.overflow != Overflow::LowerTiers
+                        || config.jackpot_cap.is_none()
+                        || !config.prize_tiers.is_empty()),
+                "invalid jackpot cap!"
+            );
+            assert!(
+                config.mode == GameMode::Numbers
+                    || (config.prize_tiers.is_empty()
+                        && config.box_bet.is_none()
+                        && config.must_be_won_after.is_none()
+                        && (config.overflow != Overflow::LowerTiers
+                            || config.jackpot_cap.is_none())),
+                "invalid raffle!"
+            );
+            assert!(
+                config.must_be_won_after.is_none()
+                    || (config.must_be_won_after != Some(0)
+                        && shape.numbers <= MAX_MUST_BE_WON_NUMBERS
+                        && (shape.ordered || !shape.duplicates)),
+                "invalid must be won rule!"
+            );
+            assert!(
+                config.runner_ups.is_empty()
+                    || (config.mode == GameMode::Numbers
+                        && config.runner_ups.len() < MAX_WINNING_TICKETS
+                        && config.runner_ups.iter().all(|share| *share > 0)
+                        && config
+                            .runner_ups
+                            .iter()
+                            .map(|share| Balance::from(*share))
+                            .sum::<Balance>()
+                            <= BASIS_POINTS
+                        && combinations(&config) > config.runner_ups.len() as u128),
+                "invalid runner-ups!"
+            );
+            if let Some(odds) = config.fixed_odds {
+                assert!(
+                    config.mode == GameMode::FixedOdds
+                        && odds.multiplier > 0
+                        && odds.max_ticket_exposure > 0
+                        && odds.max_round_exposure > 0
+                        && config.prize_tiers.is_empty()
+                        && config.box_bet.is_none()
+                        && config.must_be_won_after.is_none()
+                        && config.reserve_share == 0
+                        && config.min_jackpot == 0
+                        && config.jackpot_cap.is_none(),
+                    "invalid fixed odds!"
+                );
+            } else {
+                assert!(config.mode != GameMode::FixedOdds, "invalid fixed odds!");
+            }
+            ink_lang::utils::initialize_contract(|contract: &mut Self| contract.new_init(config))
         }
 
-        fn new_init(&mut self) {
-            let ticket = [0; 3];
-            self.round = 0;
-            self.ticket_and_address
-                .insert((ticket, 0), &[AccountId::default(); 8]);
+        fn new_init(&mut self, config: LotteryConfig) {
+            self.state.version = STATE_VERSION;
+            self.state.round = 0;
+            self.state.winner_tickets = vec![vec![0; ticket_len(&config)]];
+            self.state.config = config;
+            self.state.draw_count = 0;
             self.jackpot = 0;
+            self.state.unclaimed_prizes = 0;
             self.last_jackpot = 0;
             self.last_drawing = self.env().block_number();
-            self.default_address = [AccountId::default(); 8];
-            self.winner_ticket = [0; 3];
+            self.state.first_payout = 0;
+            self.state.payout_count = 0;
+            self.state.pending_payouts = 0;
             self.last_pot_per_ticket = 0;
+            self.state.owner = self.env().caller();
+            self.state.treasury = 0;
+            self.state.reserve = 0;
+            self.state.seeded_total = 0;
+            self.state.secondary_pot = 0;
+            self.state.second_chance_pot = 0;
+            self.state.bankroll = 0;
+            self.state.round_exposure = 0;
+            self.state.lp_total_shares = 0;
+            self.state.depositor_count = 0;
+            self.state.total_principal = 0;
+            self.state.subscription_count = 0;
+            self.state.active_subscription_count = 0;
+            self.state.subscriptions_entered = 0;
+            self.state.subscription_funds = 0;
+            self.state.balances_total = 0;
+            self.state.last_raffle_winner = None;
         }
 
-        /// Register specific ticket with caller as owner.
+        /// Register `quantity` shares of specific ticket with caller as owner.
         #[ink(message, payable)]
-        pub fn register_ticket(&mut self, ticket: [u8; 3]) -> Result<()> {
-            let trans_bal = self.env().transferred_value();
-            assert!(trans_bal == BET_PRICE, "insufficient funds!");
-            self.jackpot += trans_bal;
+        pub fn register_ticket(&mut self, ticket: Ticket, quantity: u32) -> Result<()> {
+            if self.migration_pending() {
+                return self.refund(Error::MigrationPending);
+            }
+            let price = BET_PRICE * Balance::from(quantity);
+            if quantity == 0 {
+                return self.refund(Error::InvalidQuantity);
+            }
+            if matches!(self.state.config.mode, GameMode::Raffle | GameMode::NoLoss) {
+                return self.refund(Error::WrongMode);
+            }
+            let ticket = match self.checked_ticket(ticket) {
+                Ok(ticket) => ticket,
+                Err(error) => return self.refund(error),
+            };
+            let round_exposure = match self.checked_exposure(&ticket, quantity, price) {
+                Ok(round_exposure) => round_exposure,
+                Err(error) => return self.refund(error),
+            };
+            self.charge(price)?;
+            self.state.round_exposure = round_exposure;
+            self.collect_sale(price);
 
             let caller = self.env().caller();
+            self.add_shares(
+                BetKind::Ticket,
+                &ticket,
+                self.state.round,
+                caller,
+                self.state.draw_count,
+                quantity,
+            );
+            self.count_matches(&ticket, quantity);
+            self.env().emit_event(RegisterTicket {
+                ticket,
+                from: caller,
+                shares: quantity,
+            });
 
-            if self.ticket_and_address.contains((ticket, self.round)) {
-                let mut ticket_buyer = self.ticket_and_address.get((ticket, self.round)).unwrap();
-                assert!(ticket_buyer[7] == AccountId::default(), "ticket sold out!");
-                for i in 0..ticket_buyer.len() {
-                    if ticket_buyer[i] == AccountId::default() {
-                        ticket_buyer[i] = caller;
-                        self.ticket_and_address
-                            .insert((ticket, self.round), &ticket_buyer);
-                        self.env().emit_event(RegisterTicket {
-                            ticket,
-                            from: caller,
-                        });
-                        break;
-                    }
+            self.draw_if_due();
+            Ok(())
+        }
+
+        /// Cancels the shares of `ticket` the caller bought in `round` since the last drawing,
+        /// allowed until `CANCELLATION_CUTOFF` blocks before the next drawing. Refunds the price
+        /// minus the house fee of the sale, which stays in the treasury, or minus the cancellation
+        /// fee if it is higher, the difference going to the treasury too.
+        #[ink(message)]
+        pub fn cancel_ticket(&mut self, ticket: Ticket, round: u32) -> Result<Balance> {
+            if self.migration_pending() {
+                return Err(Error::MigrationPending);
+            }
+            if matches!(self.state.config.mode, GameMode::Raffle | GameMode::NoLoss) {
+                return Err(Error::WrongMode);
+            }
+            let now = self.env().block_number();
+            if round != self.state.round
+                || now + CANCELLATION_CUTOFF >= self.last_drawing + BLOCKS_PER_ROUND
+            {
+                return Err(Error::CancellationClosed);
+            }
+            let ticket = self.checked_ticket(ticket)?;
+            let caller = self.env().caller();
+            let key = (BetKind::Ticket, &ticket, round, caller);
+            let mut entries = self.holder_entries.get(key).unwrap_or_default();
+            let entry = match entries.last() {
+                Some(entry) if entry.draw == self.state.draw_count => *entry,
+                _ => return Err(Error::NothingToCancel),
+            };
+            let shares = entry.shares;
+            let value = BET_PRICE * Balance::from(shares);
+            let fee = value * Balance::from(self.state.config.cancellation_fee) / BASIS_POINTS;
+            let house_fee = value * Balance::from(self.state.config.house_fee) / BASIS_POINTS;
+            let refund = value - fee.max(house_fee);
+            self.env()
+                .transfer(caller, refund)
+                .map_err(|_| Error::TransferFailed)?;
+            self.state.treasury += fee.saturating_sub(house_fee);
+            self.reverse_sale(value);
+            if let Some(odds) = self.state.config.fixed_odds {
+                self.state.round_exposure -= value * Balance::from(odds.multiplier);
+            }
+            entries.pop();
+            let mut totals = self.ticket_totals(BetKind::Ticket, &ticket, round);
+            totals.shares -= shares;
+            let mut stats = self.round_stats.get(round).unwrap_or_default();
+            stats.tickets_sold -= entry.purchases;
+            stats.shares_sold -= shares;
+            stats.total_collected -= value;
+            if entries.is_empty() {
+                self.holder_entries.remove(key);
+                self.remove_holder(BetKind::Ticket, &ticket, round, caller, &mut totals);
+                if totals.holders == 0 {
+                    stats.distinct_combinations -= 1;
+                }
+                if self.remove_account_ticket(BetKind::Ticket, &ticket, round, caller) {
+                    self.remove_round_player(round, caller, &mut stats);
                 }
             } else {
-                let mut ticket_buyer: [AccountId; 8] = [AccountId::default(); 8];
-                ticket_buyer[0] = caller;
-                self.ticket_and_address
-                    .insert((ticket, self.round), &ticket_buyer);
-                self.env().emit_event(RegisterTicket {
-                    ticket,
-                    from: caller,
-                });
+                self.holder_entries.insert(key, &entries);
             }
+            self.ticket_totals
+                .insert((BetKind::Ticket, &ticket, round), &totals);
+            self.round_stats.insert(round, &stats);
+            self.update_match_counts(&ticket, |count| count - shares);
+            self.env().emit_event(TicketCancelled {
+                ticket,
+                from: caller,
+                shares,
+                refund,
+            });
+            Ok(refund)
+        }
 
-            let now = self.env().block_number();
-            if now - self.last_drawing >= BLOCKS_PER_ROUND && now != 0 {
-                self.draw();
+        /// Register a box ticket with caller as owner, it wins if the drawn numbers match in any order.
+        #[ink(message, payable)]
+        pub fn register_box_ticket(&mut self, ticket: Ticket) -> Result<()> {
+            if self.migration_pending() {
+                return self.refund(Error::MigrationPending);
             }
+            let box_bet = match self.state.config.box_bet {
+                Some(box_bet) => box_bet,
+                None => return self.refund(Error::BoxBetsDisabled),
+            };
+            let ticket = match self.checked_ticket(ticket) {
+                Ok(ticket) => sorted_main(ticket, self.state.config.ticket),
+                Err(error) => return self.refund(error),
+            };
+            self.charge(box_bet.price)?;
+            self.collect_sale(box_bet.price);
+
+            let caller = self.env().caller();
+            self.add_shares(
+                BetKind::Box,
+                &ticket,
+                self.state.round,
+                caller,
+                self.state.draw_count,
+                1,
+            );
+            self.env().emit_event(RegisterBoxTicket {
+                ticket,
+                from: caller,
+            });
+
+            self.draw_if_due();
             Ok(())
         }
 
-        fn draw(&mut self) {
-            let rand_output = self.env().extension().fetch_random().unwrap();
+        /// Buys `quantity` entries of the raffle of the actual round, the drawing picks one of
+        /// all entries sold.
+        #[ink(message, payable)]
+        pub fn enter_raffle(&mut self, quantity: u32) -> Result<()> {
+            if self.migration_pending() {
+                return self.refund(Error::MigrationPending);
+            }
+            let price = BET_PRICE * Balance::from(quantity);
+            if quantity == 0 {
+                return self.refund(Error::InvalidQuantity);
+            }
+            if self.state.config.mode != GameMode::Raffle {
+                return self.refund(Error::WrongMode);
+            }
+            self.charge(price)?;
+            self.collect_sale(price);
 
-            let mut win_ticket: [u8; 3] = [0; 3];
-            win_ticket[0] = rand_output[0];
-            win_ticket[1] = rand_output[1];
-            win_ticket[2] = rand_output[2];
-            self.winner_ticket = win_ticket;
-            self.last_drawing = self.env().block_number();
+            let caller = self.env().caller();
+            let ticket = Ticket::new();
+            let start = self
+                .ticket_totals(BetKind::Raffle, &ticket, self.state.round)
+                .shares;
+            let entries = self.raffle_entry_count.get(self.state.round).unwrap_or(0);
+            self.raffle_entries.insert(
+                (self.state.round, entries),
+                &RaffleEntry {
+                    holder: caller,
+                    start,
+                },
+            );
+            self.raffle_entry_count
+                .insert(self.state.round, &(entries + 1));
+            self.add_shares(
+                BetKind::Raffle,
+                &ticket,
+                self.state.round,
+                caller,
+                self.state.draw_count,
+                quantity,
+            );
+            self.env().emit_event(RegisterTicket {
+                ticket,
+                from: caller,
+                shares: quantity,
+            });
 
-            let winners = self.get_winner_or_default();
-            if winners != self.default_address {
-                self.transfer_to_winners(winners);
-            }
+            self.draw_if_due();
+            Ok(())
         }
 
-        fn transfer_to_winners(&mut self, winners: [AccountId; 8]) {
-            if self.jackpot > 0 {
-                let number_of_winners = self.get_number_of_winner(winners);
-                let jackpot_balance: Balance = self.jackpot / 8;
-                if number_of_winners > 0 {
-                    let jack_multiplication = 8 / number_of_winners;
-                    self.last_pot_per_ticket = jackpot_balance * u128::from(jack_multiplication);
-                    for winner in 0..number_of_winners {
-                        let winner_id = winners[usize::from(winner)];
-                        if winner_id != AccountId::default() {
-                            let _res = self.env().transfer(winner_id, self.last_pot_per_ticket);
-                        }
-                    }
-                    self.reset_game()
-                }
+        /// Adds the transferred value to the balance of the caller, purchases sending no value
+        /// are paid from it.
+        #[ink(message, payable)]
+        pub fn deposit(&mut self) -> Result<Balance> {
+            if self.migration_pending() {
+                return self.refund(Error::MigrationPending);
             }
+            let amount = self.env().transferred_value();
+            if amount == 0 {
+                return Err(Error::NoValue);
+            }
+            let caller = self.env().caller();
+            let balance = self.balances.get(caller).unwrap_or(0);
+            self.set_balance(caller, balance, balance + amount);
+            Ok(balance + amount)
         }
 
-        fn get_number_of_winner(&mut self, winners: [AccountId; 8]) -> u8 {
-            let mut count = 0;
-            for i in 0..8 {
-                if winners[i] != AccountId::default() {
-                    count += 1;
-                }
+        /// Sends `amount` of the balance of the caller back.
+        #[ink(message)]
+        pub fn withdraw_balance(&mut self, amount: Balance) -> Result<()> {
+            if self.migration_pending() {
+                return Err(Error::MigrationPending);
+            }
+            let caller = self.env().caller();
+            let balance = self.balances.get(caller).unwrap_or(0);
+            if amount == 0 || amount > balance {
+                return Err(Error::InsufficientBalance);
             }
-            count
+            self.env()
+                .transfer(caller, amount)
+                .map_err(|_| Error::TransferFailed)?;
+            self.set_balance(caller, balance, balance - amount);
+            Ok(())
         }
 
-        fn reset_game(&mut self) {
-            self.round += 1;
-            self.last_jackpot = self.jackpot;
-            self.jackpot = 0;
+        /// Lets the prizes of the caller be credited to its balance instead of sent.
+        #[ink(message)]
+        pub fn set_prizes_to_balance(&mut self, enabled: bool) -> Result<()> {
+            if self.migration_pending() {
+                return Err(Error::MigrationPending);
+            }
+            let caller = self.env().caller();
+            if enabled {
+                self.prizes_to_balance.insert(caller, &());
+            } else {
+                self.prizes_to_balance.remove(caller);
+            }
+            Ok(())
         }
 
-        fn get_winner_or_default(&self) -> [AccountId; 8] {
-            self.ticket_and_address
-                .get((self.winner_ticket, self.round))
-                .unwrap_or(self.default_address)
+        /// Prepays `ticket` for `rounds` rounds, it is entered for the actual round right away
+        /// and for the next ones by the batches of `enter_subscriptions`. Returns the id of the
+        /// subscription.
+        #[ink(message, payable)]
+        pub fn subscribe(&mut self, ticket: Ticket, rounds: u32) -> Result<u32> {
+            if self.migration_pending() {
+                return self.refund(Error::MigrationPending);
+            }
+            let price = BET_PRICE * Balance::from(rounds);
+            if rounds == 0 {
+                return self.refund(Error::InvalidQuantity);
+            }
+            if self.state.config.mode != GameMode::Numbers {
+                return self.refund(Error::WrongMode);
+            }
+            let holder = self.env().caller();
+            let mut ids = self.account_subscriptions.get(holder).unwrap_or_default();
+            if ids.len() >= MAX_ACCOUNT_SUBSCRIPTIONS {
+                return self.refund(Error::TooManySubscriptions);
+            }
+            let ticket = match self.checked_ticket(ticket) {
+                Ok(ticket) => ticket,
+                Err(error) => return self.refund(error),
+            };
+            self.charge(price)?;
+            let id = self.state.subscription_count;
+            self.state.subscription_count += 1;
+            ids.push(id);
+            self.account_subscriptions.insert(holder, &ids);
+            self.state.subscription_funds += price;
+            let subscription = Subscription {
+                id,
+                holder,
+                ticket,
+                rounds_left: rounds,
+            };
+            if self.enter_subscription(subscription) {
+                // keeps the entered subscriptions in front of the ones still to enter
+                let entered = self.state.subscriptions_entered;
+                if entered < self.state.active_subscription_count {
+                    let pending = self.active_subscriptions.get(entered).unwrap();
+                    self.active_subscriptions
+                        .insert(self.state.active_subscription_count, &pending);
+                }
+                self.active_subscriptions.insert(entered, &id);
+                self.state.active_subscription_count += 1;
+                self.state.subscriptions_entered += 1;
+            }
+
+            self.draw_if_due();
+            Ok(id)
         }
 
-        /// returns the winner ticket
+        /// Ends the subscription `id` of the caller and refunds the rounds not entered yet.
         #[ink(message)]
-        pub fn get_winner_ticket(&self) -> [u8; 3] {
-            self.winner_ticket
+        pub fn cancel_subscription(&mut self, id: u32) -> Result<Balance> {
+            if self.migration_pending() {
+                return Err(Error::MigrationPending);
+            }
+            let caller = self.env().caller();
+            let subscription = self
+                .subscriptions
+                .get(id)
+                .filter(|subscription| {
+                    subscription.holder == caller && subscription.rounds_left > 0
+                })
+                .ok_or(Error::SubscriptionNotFound)?;
+            let amount = BET_PRICE * Balance::from(subscription.rounds_left);
+            self.env()
+                .transfer(caller, amount)
+                .map_err(|_| Error::TransferFailed)?;
+            self.state.subscription_funds -= amount;
+            self.end_subscription(subscription);
+            Ok(amount)
         }
-        /// returns all accounts per ticket for the actual run
-        #[ink(message)]
-        pub fn get_accounts_by_ticket(&self, ticket_hash: [u8; 3]) -> [AccountId; 8] {
-            self.ticket_and_address
-                .get((ticket_hash, self.round))
-                .unwrap_or(self.default_address)
+
+        /// Enters the ticket of `subscription` for the actual round paid from its prepaid
+        /// rounds, returns true if rounds are left.
+        fn enter_subscription(&mut self, mut subscription: Subscription) -> bool {
+            self.state.subscription_funds -= BET_PRICE;
+            self.collect_sale(BET_PRICE);
+            self.add_shares(
+                BetKind::Ticket,
+                &subscription.ticket,
+                self.state.round,
+                subscription.holder,
+                self.state.draw_count,
+                1,
+            );
+            self.count_matches(&subscription.ticket, 1);
+            self.env().emit_event(RegisterTicket {
+                ticket: subscription.ticket.clone(),
+                from: subscription.holder,
+                shares: 1,
+            });
+            subscription.rounds_left -= 1;
+            if subscription.rounds_left == 0 {
+                self.end_subscription(subscription);
+                return false;
+            }
+            self.subscriptions.insert(subscription.id, &subscription);
+            true
         }
 
-        /// returns the actual jackpot
-        #[ink(message)]
-        pub fn get_jackpot(&self) -> Balance {
-            self.jackpot
+        /// Enters up to `max_subscriptions` active subscriptions which are not entered for the
+        /// actual round yet, ended ones are dropped on the way. Returns the number entered.
+        fn enter_pending_subscriptions(&mut self, max_subscriptions: u32) -> u32 {
+            let mut entered = 0;
+            while entered < max_subscriptions
+                && self.state.subscriptions_entered < self.state.active_subscription_count
+            {
+                let index = self.state.subscriptions_entered;
+                let id = self.active_subscriptions.get(index).unwrap();
+                let subscription = self.subscriptions.get(id).unwrap();
+                if subscription.rounds_left > 0 {
+                    entered += 1;
+                    if self.enter_subscription(subscription) {
+                        self.state.subscriptions_entered += 1;
+                        continue;
+                    }
+                }
+                let last = self.state.active_subscription_count - 1;
+                if index != last {
+                    let moved = self.active_subscriptions.get(last).unwrap();
+                    self.active_subscriptions.insert(index, &moved);
+                }
+                self.active_subscriptions.remove(last);
+                self.state.active_subscription_count = last;
+            }
+            entered
         }
 
-        /// returns the block of the last drawing
-        #[ink(message)]
-        pub fn get_last_drawing(&self) -> BlockNumber {
-            self.last_drawing
+        /// Ends `subscription`, it stays in the active subscriptions until the next batch
+        /// entering them passes it.
+        fn end_subscription(&mut self, mut subscription: Subscription) {
+            let mut ids = self
+                .account_subscriptions
+                .get(subscription.holder)
+                .unwrap_or_default();
+            ids.retain(|id| *id != subscription.id);
+            self.account_subscriptions.insert(subscription.holder, &ids);
+            subscription.rounds_left = 0;
+            self.subscriptions.insert(subscription.id, &subscription);
         }
 
-        /// returns the end jackpot of the last round
-        #[ink(message)]
-        pub fn get_last_jackpot(&self) -> Balance {
-            self.last_jackpot
+        /// Adds the transferred value to the principal of the caller in the no-loss mode, the
+        /// principal counts for the drawings from now on and has to reach `MIN_PRINCIPAL`.
+        #[ink(message, payable)]
+        pub fn deposit_principal(&mut self) -> Result<()> {
+            if self.migration_pending() {
+                return self.refund(Error::MigrationPending);
+            }
+            let amount = self.env().transferred_value();
+            if amount == 0 {
+                return Err(Error::NoValue);
+            }
+            if self.state.config.mode != GameMode::NoLoss {
+                return self.refund(Error::WrongMode);
+            }
+            let caller = self.env().caller();
+            let held = self
+                .depositors
+                .get(caller)
+                .map_or(0, |depositor| depositor.principal);
+            if held + amount < MIN_PRINCIPAL {
+                return self.refund(Error::InsufficientPrincipal);
+            }
+            let mut depositor = match self.depositors.get(caller) {
+                Some(depositor) => depositor,
+                None if self.state.depositor_count < MAX_DEPOSITORS => {
+                    self.depositor_accounts
+                        .insert(self.state.depositor_count, &caller);
+                    self.state.depositor_count += 1;
+                    Depositor {
+                        index: self.state.depositor_count - 1,
+                        ..Default::default()
+                    }
+                }
+                None => return self.refund(Error::TooManyDepositors),
+            };
+            self.update_weight(&mut depositor);
+            depositor.principal += amount;
+            self.depositors.insert(caller, &depositor);
+            self.state.total_principal += amount;
+
+            self.draw_if_due();
+            Ok(())
         }
 
-        /// returns the array of the last winners or the default address
+        /// Sends `amount` of the principal of the caller back, allowed at any time as long as no
+        /// principal below `MIN_PRINCIPAL` is left.
         #[ink(message)]
-        pub fn get_last_winner_or_default(&self) -> [AccountId; 8] {
-            if self.round == 0 {
-                return self.default_address;
-            } else {
-                return self
-                    .ticket_and_address
-                    .get((self.winner_ticket, self.round - 1))
-                    .unwrap_or(self.default_address);
+        pub fn withdraw_principal(&mut self, amount: Balance) -> Result<()> {
+            if self.migration_pending() {
+                return Err(Error::MigrationPending);
+            }
+            let caller = self.env().caller();
+            let mut depositor = self.depositors.get(caller).unwrap_or_default();
+            let left = depositor.principal.saturating_sub(amount);
+            if amount == 0 || amount > depositor.principal || (left > 0 && left < MIN_PRINCIPAL) {
+                return Err(Error::InsufficientPrincipal);
             }
+            self.env()
+                .transfer(caller, amount)
+                .map_err(|_| Error::TransferFailed)?;
+            self.update_weight(&mut depositor);
+            depositor.principal -= amount;
+            self.depositors.insert(caller, &depositor);
+            self.state.total_principal -= amount;
+            Ok(())
         }
 
-        /// returns the block of the last drawing
-        #[ink(message)]
-        pub fn get_next_drawing(&self) -> BlockNumber {
-            self.last_drawing + BLOCKS_PER_ROUND
+        /// Weight of `depositor` in the drawing period `period` which started at block `start`,
+        /// its principal times the blocks it was held up to `now`.
+        fn weight_at(
+            depositor: &Depositor,
+            period: u32,
+            start: BlockNumber,
+            now: BlockNumber,
+        ) -> Balance {
+            if depositor.period == period {
+                depositor.weight
+                    + depositor.principal * Balance::from(now.saturating_sub(depositor.updated))
+            } else {
+                depositor.principal * Balance::from(now.saturating_sub(start))
+            }
         }
 
-        /// returns the price per winner of the last round
-        #[ink(message)]
-        pub fn get_last_pot_per_ticket(&self) -> Balance {
-            self.last_pot_per_ticket
+        /// Brings the weight of `depositor` up to the actual block before its principal changes.
+        fn update_weight(&self, depositor: &mut Depositor) {
+            let now = self.env().block_number();
+            depositor.weight =
+                Self::weight_at(depositor, self.state.draw_count, self.last_drawing, now);
+            depositor.period = self.state.draw_count;
+            depositor.updated = now;
         }
-    }
 
-    /// Unit tests in Rust are normally defined within such a `#[cfg(test)]`
-    /// module and test functions are marked with a `#[test]` attribute.
+        /// Adds the transferred value to the jackpot of the actual round or of the future round
+        /// `round_target`.
+        #[ink(message, payable)]
+        pub fn seed_jackpot(&mut self, round_target: Option<u32>, memo: Vec<u8>) -> Result<()> {
+            if self.migration_pending() {
+                return self.refund(Error::MigrationPending);
+            }
+            let amount = self.env().transferred_value();
+            if amount == 0 {
+                return Err(Error::NoValue);
+            }
+            if self.state.config.mode == GameMode::FixedOdds {
+                return self.refund(Error::WrongMode);
+            }
+            let round = round_target.unwrap_or(self.state.round);
+            if round < self.state.round {
+                return self.refund(Error::InvalidRound);
+            }
+            if round == self.state.round {
+                self.jackpot += amount;
+            } else {
+                let seeded = self.seeded_jackpots.get(round).unwrap_or(0);
+                self.seeded_jackpots.insert(round, &(seeded + amount));
+                self.state.seeded_total += amount;
+            }
+            let sponsor = self.env().caller();
+            let total = self.sponsor_totals.get(sponsor).unwrap_or(0);
+            self.sponsor_totals.insert(sponsor, &(total + amount));
+            self.env().emit_event(JackpotSeeded {
+                sponsor,
+                round,
+                amount,
+                memo,
+            });
+            Ok(())
+        }
+
+        /// Claims the tier prize of the callers shares of `ticket` in drawing `draw`.
+        #[ink(message)]
+        pub fn claim_prize(&mut self, draw: u32, ticket: Ticket) -> Result<Balance> {
+            if self.migration_pending() {
+                return Err(Error::MigrationPending);
+            }
+            let result = self.draw_results.get(draw).ok_or(Error::DrawNotFound)?;
+            let ticket = self.checked_ticket(ticket)?;
+            let caller = self.env().caller();
+            let amount = self.tier_prize(draw, &result, &ticket, caller)?;
+
+            self.send_prize(caller, amount)?;
+            self.claimed.insert((draw, &ticket, caller), &());
+            self.state.unclaimed_prizes -= amount;
+            self.env().emit_event(PrizeClaimed {
+                draw,
+                ticket,
+                to: caller,
+                amount,
+            });
+            Ok(amount)
+        }
+
+        /// Tier prize of the shares `holder` held of `ticket` in drawing `draw` which is not
+        /// claimed yet.
+        fn tier_prize(
+            &self,
+            draw: u32,
+            result: &DrawResult,
+            ticket: &Ticket,
+            holder: AccountId,
+        ) -> Result<Balance> {
+            let winner = &result.winning_tickets.first().ok_or(Error::NoPrize)?.ticket;
+            let matches = matching_numbers(ticket, winner, self.state.config.ticket);
+            let bonus = self.state.config.bonus.is_some() && ticket.last() == winner.last();
+            let mut prize = result
+                .tiers
+                .iter()
+                .find(|tier| tier.matches == matches && tier.bonus == bonus)
+                .map(|tier| tier.prize_per_winner);
+            if let Some(closest) = &result.closest_matches {
+                if closest.matches == matches {
+                    prize = Some(prize.unwrap_or(0) + closest.prize_per_winner);
+                }
+            }
+            let prize = prize.ok_or(Error::NoPrize)?;
+
+            if self.claimed.contains((draw, ticket, holder)) {
+                return Err(Error::PrizeAlreadyClaimed);
+            }
+            let shares = self.holder_shares_at(BetKind::Ticket, ticket, result.round, holder, draw);
+            let amount = prize * Balance::from(shares);
+            if amount == 0 {
+                return Err(Error::NoPrize);
+            }
+            Ok(amount)
+        }
+
+        /// Prizes `account` can still claim or withdraw.
+        fn claimable(&self, account: AccountId) -> Balance {
+            self.credits.get(account).unwrap_or(0) + self.claimable_tier_prizes(account)
+        }
+
+        /// Tier prizes `account` can still claim in the drawings of the round of the last
+        /// drawing, looking back `MAX_PAGE_SIZE` drawings and tickets at most.
+        fn claimable_tier_prizes(&self, account: AccountId) -> Balance {
+            let last_draw = match self.state.draw_count.checked_sub(1) {
+                Some(last_draw) if self.min_tracked_matches().is_some() => last_draw,
+                _ => return 0,
+            };
+            let round = self.draw_results.get(last_draw).unwrap().round;
+            let tickets: Vec<Ticket> = self
+                .get_tickets_of(account, round, 0, MAX_PAGE_SIZE)
+                .into_iter()
+                .filter(|entry| entry.kind == BetKind::Ticket)
+                .map(|entry| entry.ticket)
+                .collect();
+            let mut claimable = 0;
+            for draw in (last_draw.saturating_sub(MAX_PAGE_SIZE - 1)..=last_draw).rev() {
+                let result = self.draw_results.get(draw).unwrap();
+                if result.round != round {
+                    break;
+                }
+                for ticket in tickets.iter() {
+                    claimable += self.tier_prize(draw, &result, ticket, account).unwrap_or(0);
+                }
+            }
+            claimable
+        }
+
+        /// Checks the picks against the ticket shape, unordered tickets are sorted.
+        fn checked_ticket(&self, mut ticket: Ticket) -> Result<Ticket> {
+            let shape = self.state.config.ticket;
+            let numbers = usize::from(shape.numbers);
+            if ticket.len() != ticket_len(&self.state.config)
+                || ticket[..numbers]
+                    .iter()
+                    .any(|number| *number < shape.min || *number > shape.max)
+            {
+                return Err(Error::InvalidTicket);
+            }
+            if let Some(bonus) = self.state.config.bonus {
+                if ticket[numbers] < bonus.min || ticket[numbers] > bonus.max {
+                    return Err(Error::InvalidTicket);
+                }
+            }
+            if !shape.ordered || !shape.duplicates {
+                let picks = sorted_main(ticket.clone(), shape);
+                if !shape.duplicates && picks[..numbers].windows(2).any(|pair| pair[0] == pair[1]) {
+                    return Err(Error::InvalidTicket);
+                }
+                if !shape.ordered {
+                    ticket = picks;
+                }
+            }
+            Ok(ticket)
+        }
+
+        /// Sends the transferred value back, the call doesn't revert when returning an error.
+        /// A contract upgraded from the first release accepts no calls but the migration until
+        /// `migrate_legacy_state` has run.
+        fn migration_pending(&self) -> bool {
+            self.state.version == 0
+        }
+
+        fn refund<T>(&mut self, error: Error) -> Result<T> {
+            let trans_bal = self.env().transferred_value();
+            if trans_bal > 0 {
+                let _res = self.env().transfer(self.env().caller(), trans_bal);
+            }
+            Err(error)
+        }
+
+        fn draw_is_due(&self) -> bool {
+            let now = self.env().block_number();
+            now - self.last_drawing >= BLOCKS_PER_ROUND && now != 0
+        }
+
+        fn draw_if_due(&mut self) {
+            if self.draw_is_due() {
+                self.draw();
+            }
+        }
+
+        fn draw(&mut self) {
+            let rand_output = self.env().extension().fetch_random().unwrap();
+
+            let period_start = self.last_drawing;
+            self.last_drawing = self.env().block_number();
+            let draw = self.state.draw_count;
+            self.state.draw_count += 1;
+            let mut stats = self.round_stats.get(self.state.round).unwrap_or_default();
+            stats.draws += 1;
+            self.round_stats.insert(self.state.round, &stats);
+            if self.state.config.mode == GameMode::Raffle {
+                self.draw_raffle(draw, rand_output);
+                self.pay_out(PAYOUTS_PER_DRAW);
+                return;
+            }
+            if self.state.config.mode == GameMode::FixedOdds {
+                self.draw_fixed_odds(draw, rand_output);
+                self.pay_out(PAYOUTS_PER_DRAW);
+                return;
+            }
+            if self.state.config.mode == GameMode::NoLoss {
+                self.draw_no_loss(draw, period_start, rand_output);
+                self.pay_out(PAYOUTS_PER_DRAW);
+                return;
+            }
+
+            self.state.winner_tickets = draw_winner_tickets(&self.state.config, rand_output);
+            let round = self.state.round;
+            let tiers = self.settle_prize_tiers();
+            let (box_winners, box_prize_per_winner) = self.settle_box_bets();
+
+            let jackpot_winners = self
+                .ticket_totals(
+                    BetKind::Ticket,
+                    &self.state.winner_tickets[0],
+                    self.state.round,
+                )
+                .shares;
+            let mut winning_tickets = self.settle_runner_ups();
+            let mut closest_matches = None;
+            if jackpot_winners == 0
+                && matches!(self.state.config.must_be_won_after, Some(after) if stats.draws >= after)
+            {
+                closest_matches = self.settle_closest_matches();
+            }
+            let mut prize_per_winner = 0;
+            let mut second_chance_winner = None;
+            if jackpot_winners > 0 {
+                prize_per_winner = self.transfer_to_winners();
+            } else if closest_matches.is_some() {
+                self.reset_game();
+            } else {
+                second_chance_winner = self.settle_second_chance(rand_output);
+            }
+            winning_tickets.insert(
+                0,
+                WinningTicket {
+                    ticket: self.state.winner_tickets[0].clone(),
+                    winners: jackpot_winners,
+                    prize_per_winner,
+                },
+            );
+            self.draw_results.insert(
+                draw,
+                &DrawResult {
+                    round,
+                    block: self.last_drawing,
+                    winning_tickets,
+                    tiers,
+                    box_winners,
+                    box_prize_per_winner,
+                    closest_matches,
+                    raffle_winner: None,
+                    second_chance_winner,
+                },
+            );
+            self.pay_out(PAYOUTS_PER_DRAW);
+            self.enter_pending_subscriptions(SUBSCRIPTIONS_PER_DRAW);
+        }
+
+        /// Pays the share of the jackpot of every runner-up ticket to its holders, the jackpot
+        /// is split before any of the shares is taken out.
+        fn settle_runner_ups(&mut self) -> Vec<WinningTicket> {
+            let jackpot = self.jackpot;
+            let runner_ups = self.state.winner_tickets[1..].to_vec();
+            let mut winning_tickets = Vec::new();
+            for (ticket, share) in runner_ups
+                .into_iter()
+                .zip(self.state.config.runner_ups.clone())
+            {
+                let totals = self.ticket_totals(BetKind::Ticket, &ticket, self.state.round);
+                let mut prize_per_winner = 0;
+                if totals.shares > 0 {
+                    prize_per_winner = jackpot * Balance::from(share)
+                        / BASIS_POINTS
+                        / Balance::from(totals.shares);
+                    self.jackpot -= prize_per_winner * Balance::from(totals.shares);
+                    self.queue_payout(BetKind::Ticket, ticket.clone(), totals, prize_per_winner);
+                }
+                winning_tickets.push(WinningTicket {
+                    ticket,
+                    winners: totals.shares,
+                    prize_per_winner,
+                });
+            }
+            winning_tickets
+        }
+
+        /// Picks the winning entry of the raffle out of all shares sold in the round, the winner
+        /// gets the whole jackpot.
+        fn draw_raffle(&mut self, draw: u32, seed: [u8; 32]) {
+            let shares = self
+                .ticket_totals(BetKind::Raffle, &Ticket::new(), self.state.round)
+                .shares;
+            let mut raffle_winner = None;
+            if shares > 0 {
+                let position = RandomNumbers::new(seed).next_below(shares);
+                let account = self.raffle_entry_at(position).holder;
+                self.top_up_jackpot();
+                let prize = self.jackpot;
+                self.last_pot_per_ticket = prize;
+                self.queue_account_payout(account, prize);
+                self.state.last_raffle_winner = Some(account);
+                raffle_winner = Some(RaffleWinner {
+                    position,
+                    account,
+                    prize,
+                });
+            }
+            self.draw_results.insert(
+                draw,
+                &DrawResult {
+                    round: self.state.round,
+                    block: self.last_drawing,
+                    winning_tickets: Vec::new(),
+                    tiers: Vec::new(),
+                    box_winners: 0,
+                    box_prize_per_winner: 0,
+                    closest_matches: None,
+                    raffle_winner,
+                    second_chance_winner: None,
+                },
+            );
+            if raffle_winner.is_some() {
+                self.reset_game();
+            }
+        }
+
+        /// Pays the fixed multiple of the stake to every share of the winning ticket out of the
+        /// bankroll and starts the next round, the other stakes stay in the bankroll.
+        fn draw_fixed_odds(&mut self, draw: u32, seed: [u8; 32]) {
+            let multiplier = self
+                .state
+                .config
+                .fixed_odds
+                .map_or(0, |odds| odds.multiplier);
+            self.state.winner_tickets = draw_winner_tickets(&self.state.config, seed);
+            let ticket = self.state.winner_tickets[0].clone();
+            let totals = self.ticket_totals(BetKind::Ticket, &ticket, self.state.round);
+            let prize_per_winner = BET_PRICE * Balance::from(multiplier);
+            if totals.shares > 0 {
+                self.state.bankroll -= prize_per_winner * Balance::from(totals.shares);
+                self.last_pot_per_ticket = prize_per_winner;
+                self.queue_payout(BetKind::Ticket, ticket.clone(), totals, prize_per_winner);
+            }
+            self.draw_results.insert(
+                draw,
+                &DrawResult {
+                    round: self.state.round,
+                    block: self.last_drawing,
+                    winning_tickets: vec![WinningTicket {
+                        ticket,
+                        winners: totals.shares,
+                        prize_per_winner,
+                    }],
+                    tiers: Vec::new(),
+                    box_winners: 0,
+                    box_prize_per_winner: 0,
+                    closest_matches: None,
+                    raffle_winner: None,
+                    second_chance_winner: None,
+                },
+            );
+            self.state.round_exposure = 0;
+            self.reset_game();
+        }
+
+        /// Picks one of the depositors weighted by the principal they held since the drawing
+        /// at block `start`, the winner gets the jackpot and keeps the principal.
+        fn draw_no_loss(&mut self, draw: u32, start: BlockNumber, seed: [u8; 32]) {
+            let weights: Vec<Balance> = (0..self.state.depositor_count)
+                .map(|index| {
+                    let account = self.depositor_accounts.get(index).unwrap();
+                    let depositor = self.depositors.get(account).unwrap_or_default();
+                    Self::weight_at(&depositor, draw, start, self.last_drawing)
+                })
+                .collect();
+            let total: Balance = weights.iter().sum();
+            let mut raffle_winner = None;
+            if total > 0 && self.jackpot > 0 {
+                let mut position = RandomNumbers::new(seed).next_below_wide(total);
+                let mut index = 0;
+                while position >= weights[index] {
+                    position -= weights[index];
+                    index += 1;
+                }
+                let account = self.depositor_accounts.get(index as u32).unwrap();
+                let prize = self.jackpot;
+                self.last_pot_per_ticket = prize;
+                self.queue_account_payout(account, prize);
+                self.state.last_raffle_winner = Some(account);
+                raffle_winner = Some(RaffleWinner {
+                    position: index as u32,
+                    account,
+                    prize,
+                });
+            }
+            self.draw_results.insert(
+                draw,
+                &DrawResult {
+                    round: self.state.round,
+                    block: self.last_drawing,
+                    winning_tickets: Vec::new(),
+                    tiers: Vec::new(),
+                    box_winners: 0,
+                    box_prize_per_winner: 0,
+                    closest_matches: None,
+                    raffle_winner,
+                    second_chance_winner: None,
+                },
+            );
+            for index in (0..self.state.depositor_count).rev() {
+                let account = self.depositor_accounts.get(index).unwrap();
+                if self.depositors.get(account).unwrap_or_default().principal == 0 {
+                    self.remove_depositor(account, index);
+                }
+            }
+            if raffle_winner.is_some() {
+                self.reset_game();
+            }
+        }
+
+        /// Frees the slot `index` of `account` by moving the last depositor into it, called at a
+        /// drawing once the account has no principal left to weigh in the next period.
+        fn remove_depositor(&mut self, account: AccountId, index: u32) {
+            let last = self.state.depositor_count - 1;
+            if index != last {
+                let moved = self.depositor_accounts.get(last).unwrap();
+                let mut depositor = self.depositors.get(moved).unwrap_or_default();
+                depositor.index = index;
+                self.depositors.insert(moved, &depositor);
+                self.depositor_accounts.insert(index, &moved);
+            }
+            self.depositor_accounts.remove(last);
+            self.depositors.remove(account);
+            self.state.depositor_count = last;
+        }
+
+        /// Pays the second-chance pot to a player of the actual round, every account holding a
+        /// ticket of the round has the same chance.
+        fn settle_second_chance(&mut self, seed: [u8; 32]) -> Option<RaffleWinner> {
+            let players = self
+                .round_stats
+                .get(self.state.round)
+                .unwrap_or_default()
+                .unique_players;
+            if self.state.second_chance_pot == 0 || players == 0 {
+                return None;
+            }
+            let position =
+                RandomNumbers::new(tagged_seed(seed, SECOND_CHANCE_TAG)).next_below(players);
+            let account = self.round_players.get((self.state.round, position))?;
+            let prize = self.state.second_chance_pot;
+            self.state.second_chance_pot = 0;
+            self.queue_account_payout(account, prize);
+            Some(RaffleWinner {
+                position,
+                account,
+                prize,
+            })
+        }
+
+        /// Entry of the actual round covering `position`, found by binary search over the
+        /// start positions.
+        fn raffle_entry_at(&self, position: u32) -> RaffleEntry {
+            let mut low = 0;
+            let mut high = self.raffle_entry_count.get(self.state.round).unwrap_or(0);
+            while high - low > 1 {
+                let middle = low + (high - low) / 2;
+                let entry = self.raffle_entries.get((self.state.round, middle)).unwrap();
+                if entry.start <= position {
+                    low = middle;
+                } else {
+                    high = middle;
+                }
+            }
+            self.raffle_entries.get((self.state.round, low)).unwrap()
+        }
+
+        /// Adds `shares` of a ticket taking part from drawing `draw` on to the shares of `holder`.
+        ///
+        /// Only the first purchase of an account adds a holder, later ones just update its
+        /// entries and the totals of the ticket.
+        fn add_shares(
+            &mut self,
+            kind: BetKind,
+            ticket: &Ticket,
+            round: u32,
+            holder: AccountId,
+            draw: u32,
+            shares: u32,
+        ) {
+            let mut totals = self.ticket_totals(kind, ticket, round);
+            let mut entries = self
+                .holder_entries
+                .get((kind, ticket, round, holder))
+                .unwrap_or_default();
+            let mut stats = self.round_stats.get(round).unwrap_or_default();
+            stats.shares_sold += shares;
+            if totals.holders == 0 {
+                stats.distinct_combinations += 1;
+            }
+            if !self.account_round_ticket_count.contains((holder, round)) {
+                self.round_players
+                    .insert((round, stats.unique_players), &holder);
+                self.round_player_positions
+                    .insert((round, holder), &stats.unique_players);
+                stats.unique_players += 1;
+            }
+            self.round_stats.insert(round, &stats);
+            if entries.is_empty() {
+                self.ticket_holders
+                    .insert((kind, ticket, round, totals.holders), &holder);
+                self.holder_positions
+                    .insert((kind, ticket, round, holder), &totals.holders);
+                totals.holders += 1;
+                self.add_account_ticket(kind, ticket, round, holder);
+            }
+            match entries.last_mut() {
+                Some(entry) if entry.draw == draw => {
+                    entry.shares += shares;
+                    entry.purchases += 1;
+                }
+                _ => entries.push(HolderEntry {
+                    draw,
+                    shares,
+                    purchases: 1,
+                }),
+            }
+            totals.shares += shares;
+            self.holder_entries
+                .insert((kind, ticket, round, holder), &entries);
+            self.ticket_totals.insert((kind, ticket, round), &totals);
+        }
+
+        /// Counts a purchase of the actual round paid with `value`, the house fee goes to the
+        /// treasury, the reserve share to the reserve, the second-chance share to its pot and
+        /// the rest to the jackpot.
+        fn collect_sale(&mut self, value: Balance) {
+            let fee = value * Balance::from(self.state.config.house_fee) / BASIS_POINTS;
+            let reserved = value * Balance::from(self.state.config.reserve_share) / BASIS_POINTS;
+            let second_chance =
+                value * Balance::from(self.state.config.second_chance_share) / BASIS_POINTS;
+            self.state.treasury += fee;
+            self.state.reserve += reserved;
+            self.state.second_chance_pot += second_chance;
+            let mut to_jackpot = value - fee - reserved - second_chance;
+            if self.state.config.mode == GameMode::FixedOdds {
+                self.state.bankroll += to_jackpot;
+                to_jackpot = 0;
+            }
+            if let Some(cap) = self.state.config.jackpot_cap {
+                let overflow = (self.jackpot + to_jackpot)
+                    .saturating_sub(cap)
+                    .min(to_jackpot);
+                self.state.secondary_pot += overflow;
+                to_jackpot -= overflow;
+            }
+            self.jackpot += to_jackpot;
+            let mut stats = self.round_stats.get(self.state.round).unwrap_or_default();
+            stats.tickets_sold += 1;
+            stats.total_collected += value;
+            self.round_stats.insert(self.state.round, &stats);
+        }
+
+        /// Adds the payout of `quantity` fixed-odds shares of `ticket` paid with `value` to the
+        /// exposure of the round, fails if it breaks a limit or the bankroll could not pay it.
+        fn checked_exposure(
+            &self,
+            ticket: &Ticket,
+            quantity: u32,
+            value: Balance,
+        ) -> Result<Balance> {
+            let odds = match self.state.config.fixed_odds {
+                Some(odds) => odds,
+                None => return Ok(self.state.round_exposure),
+            };
+            let payout = |shares: u32| {
+                (BET_PRICE * Balance::from(shares)).saturating_mul(Balance::from(odds.multiplier))
+            };
+            let shares = self
+                .ticket_totals(BetKind::Ticket, ticket, self.state.round)
+                .shares;
+            let round_exposure = self.state.round_exposure.saturating_add(payout(quantity));
+            let stake = value - value * Balance::from(self.state.config.house_fee) / BASIS_POINTS;
+            if payout(shares.saturating_add(quantity)) > odds.max_ticket_exposure
+                || round_exposure > odds.max_round_exposure
+                || round_exposure > self.state.bankroll + stake
+            {
+                return Err(Error::ExposureLimit);
+            }
+            Ok(round_exposure)
+        }
+
+        /// Takes `price` out of the transferred value and the balance of the caller. Value
+        /// beyond the price is refunded or credited to the balance, a shortfall is drawn from
+        /// the balance and fails with `TicketCosts` if the balance is too low.
+        fn charge(&mut self, price: Balance) -> Result<()> {
+            let paid = self.env().transferred_value();
+            let caller = self.env().caller();
+            let balance = self.balances.get(caller).unwrap_or(0);
+            if paid + balance < price {
+                return self.refund(Error::TicketCosts);
+            }
+            let mut remaining = balance;
+            if paid < price {
+                remaining -= price - paid;
+            } else if paid > price {
+                let excess = paid - price;
+                // a failed refund is credited instead
+                if self.state.config.overpayment == Overpayment::Credit
+                    || self.env().transfer(caller, excess).is_err()
+                {
+                    remaining += excess;
+                }
+            }
+            self.set_balance(caller, balance, remaining);
+            Ok(())
+        }
+
+        /// Changes the balance of `account` from `balance` to `remaining`.
+        fn set_balance(&mut self, account: AccountId, balance: Balance, remaining: Balance) {
+            if remaining != balance {
+                self.state.balances_total = self.state.balances_total - balance + remaining;
+                self.balances.insert(account, &remaining);
+            }
+        }
+
+        /// Sends `amount` to `winner` or credits it to its balance if it asked for it.
+        fn send_prize(&mut self, winner: AccountId, amount: Balance) -> Result<()> {
+            if self.prizes_to_balance.contains(winner) {
+                let balance = self.balances.get(winner).unwrap_or(0);
+                self.set_balance(winner, balance, balance + amount);
+                Ok(())
+            } else {
+                self.env()
+                    .transfer(winner, amount)
+                    .map_err(|_| Error::TransferFailed)
+            }
+        }
+
+        /// Takes a sale of `value` of the actual round back out of the funds `collect_sale` put
+        /// it in but the treasury, the jackpot part first out of the jackpot and then out of the
+        /// secondary pot.
+        fn reverse_sale(&mut self, value: Balance) {
+            let fee = value * Balance::from(self.state.config.house_fee) / BASIS_POINTS;
+            let reserved = value * Balance::from(self.state.config.reserve_share) / BASIS_POINTS;
+            let second_chance =
+                value * Balance::from(self.state.config.second_chance_share) / BASIS_POINTS;
+            self.state.reserve -= reserved;
+            self.state.second_chance_pot -= second_chance;
+            let to_jackpot = value - fee - reserved - second_chance;
+            if self.state.config.mode == GameMode::FixedOdds {
+                self.state.bankroll -= to_jackpot;
+            } else {
+                let from_jackpot = to_jackpot.min(self.jackpot);
+                self.jackpot -= from_jackpot;
+                self.state.secondary_pot -= to_jackpot - from_jackpot;
+            }
+        }
+
+        fn add_account_ticket(
+            &mut self,
+            kind: BetKind,
+            ticket: &Ticket,
+            round: u32,
+            holder: AccountId,
+        ) {
+            let position = self.account_ticket_count.get(holder).unwrap_or(0);
+            self.account_tickets
+                .insert((holder, position), &(kind, round, ticket.clone()));
+            self.account_ticket_count.insert(holder, &(position + 1));
+            let round_tickets = self
+                .account_round_ticket_count
+                .get((holder, round))
+                .unwrap_or(0);
+            self.account_round_tickets
+                .insert((holder, round, round_tickets), &position);
+            self.account_round_ticket_count
+                .insert((holder, round), &(round_tickets + 1));
+        }
+
+        /// Removes `holder` from the holders of `ticket` by moving the last holder into its
+        /// index. Only holders who joined in the actual drawing are removed, the moved holder
+        /// joined after them and so after every queued payout of the ticket.
+        fn remove_holder(
+            &mut self,
+            kind: BetKind,
+            ticket: &Ticket,
+            round: u32,
+            holder: AccountId,
+            totals: &mut TicketTotals,
+        ) {
+            let position = self
+                .holder_positions
+                .get((kind, ticket, round, holder))
+                .unwrap();
+            totals.holders -= 1;
+            if position != totals.holders {
+                let moved = self
+                    .ticket_holders
+                    .get((kind, ticket, round, totals.holders))
+                    .unwrap();
+                self.ticket_holders
+                    .insert((kind, ticket, round, position), &moved);
+                self.holder_positions
+                    .insert((kind, ticket, round, moved), &position);
+            }
+            self.ticket_holders
+                .remove((kind, ticket, round, totals.holders));
+            self.holder_positions.remove((kind, ticket, round, holder));
+        }
+
+        /// Removes `ticket` from the tickets of `holder`, the last ticket of the account takes
+        /// its position. Returns true if the account holds no other ticket in `round`.
+        fn remove_account_ticket(
+            &mut self,
+            kind: BetKind,
+            ticket: &Ticket,
+            round: u32,
+            holder: AccountId,
+        ) -> bool {
+            let round_tickets = self
+                .account_round_ticket_count
+                .get((holder, round))
+                .unwrap_or(0);
+            let (index, position) = (0..round_tickets)
+                .filter_map(|index| {
+                    let position = self.account_round_tickets.get((holder, round, index))?;
+                    let (held_kind, _, held) = self.account_tickets.get((holder, position))?;
+                    (held_kind == kind && &held == ticket).then_some((index, position))
+                })
+                .next()
+                .unwrap();
+            let last_index = round_tickets - 1;
+            if index != last_index {
+                let moved = self
+                    .account_round_tickets
+                    .get((holder, round, last_index))
+                    .unwrap();
+                self.account_round_tickets
+                    .insert((holder, round, index), &moved);
+            }
+            self.account_round_tickets
+                .remove((holder, round, last_index));
+            if last_index == 0 {
+                self.account_round_ticket_count.remove((holder, round));
+            } else {
+                self.account_round_ticket_count
+                    .insert((holder, round), &last_index);
+            }
+
+            let last = self.account_ticket_count.get(holder).unwrap() - 1;
+            if position != last {
+                let moved = self.account_tickets.get((holder, last)).unwrap();
+                let moved_round = moved.1;
+                let moved_count = self
+                    .account_round_ticket_count
+                    .get((holder, moved_round))
+                    .unwrap_or(0);
+                if let Some(moved_index) = (0..moved_count).find(|index| {
+                    self.account_round_tickets
+                        .get((holder, moved_round, *index))
+                        == Some(last)
+                }) {
+                    self.account_round_tickets
+                        .insert((holder, moved_round, moved_index), &position);
+                }
+                self.account_tickets.insert((holder, position), &moved);
+            }
+            self.account_tickets.remove((holder, last));
+            self.account_ticket_count.insert(holder, &last);
+            last_index == 0
+        }
+
+        /// Removes `holder` from the players of `round`, the last player takes its index.
+        fn remove_round_player(&mut self, round: u32, holder: AccountId, stats: &mut RoundStats) {
+            let position = self.round_player_positions.get((round, holder)).unwrap();
+            stats.unique_players -= 1;
+            if position != stats.unique_players {
+                let moved = self
+                    .round_players
+                    .get((round, stats.unique_players))
+                    .unwrap();
+                self.round_players.insert((round, position), &moved);
+                self.round_player_positions
+                    .insert((round, moved), &position);
+            }
+            self.round_players.remove((round, stats.unique_players));
+            self.round_player_positions.remove((round, holder));
+        }
+
+        /// Ticket at `position` of the tickets of `account` with the shares held.
+        fn account_ticket(&self, account: AccountId, position: u32) -> Option<AccountTicket> {
+            let (kind, round, ticket) = self.account_tickets.get((account, position))?;
+            let shares = self.holder_shares(kind, &ticket, round, account);
+            Some(AccountTicket {
+                kind,
+                round,
+                ticket,
+                shares,
+            })
+        }
+
+        fn liabilities(&self) -> Balance {
+            self.jackpot
+                + self.state.treasury
+                + self.state.unclaimed_prizes
+                + self.state.reserve
+                + self.state.seeded_total
+                + self.state.secondary_pot
+                + self.state.second_chance_pot
+                + self.state.bankroll
+                + self.state.total_principal
+                + self.state.subscription_funds
+                + self.state.balances_total
+        }
+
+        fn ledger(&self) -> Ledger {
+            Ledger {
+                jackpot: self.jackpot,
+                treasury: self.state.treasury,
+                claimable: self.state.unclaimed_prizes,
+                reserve: self.state.reserve,
+                seeded: self.state.seeded_total,
+                secondary_pot: self.state.secondary_pot,
+                second_chance_pot: self.state.second_chance_pot,
+                bankroll: self.state.bankroll,
+                principal: self.state.total_principal,
+                subscriptions: self.state.subscription_funds,
+                balances: self.state.balances_total,
+                unallocated: self.env().balance().saturating_sub(self.liabilities()),
+            }
+        }
+
+        fn ticket_totals(&self, kind: BetKind, ticket: &Ticket, round: u32) -> TicketTotals {
+            self.ticket_totals
+                .get((kind, ticket, round))
+                .unwrap_or_default()
+        }
+
+        fn holder_shares(
+            &self,
+            kind: BetKind,
+            ticket: &Ticket,
+            round: u32,
+            holder: AccountId,
+        ) -> u32 {
+            self.holder_entries
+                .get((kind, ticket, round, holder))
+                .unwrap_or_default()
+                .iter()
+                .map(|entry| entry.shares)
+                .sum()
+        }
+
+        /// Shares `holder` held of `ticket` in drawing `draw`, bought before or for it.
+        fn holder_shares_at(
+            &self,
+            kind: BetKind,
+            ticket: &Ticket,
+            round: u32,
+            holder: AccountId,
+            draw: u32,
+        ) -> u32 {
+            self.holder_entries
+                .get((kind, ticket, round, holder))
+                .unwrap_or_default()
+                .iter()
+                .filter(|entry| entry.draw <= draw)
+                .map(|entry| entry.shares)
+                .sum()
+        }
+
+        /// `count` holders of a ticket starting at holder `from`.
+        fn holders(
+            &self,
+            kind: BetKind,
+            ticket: &Ticket,
+            round: u32,
+            from: u32,
+            count: u32,
+        ) -> Vec<AccountId> {
+            let to = self
+                .ticket_totals(kind, ticket, round)
+                .holders
+                .min(from.saturating_add(count.min(MAX_PAGE_SIZE)));
+            (from..to)
+                .filter_map(|entry| self.ticket_holders.get((kind, ticket, round, entry)))
+                .collect()
+        }
+
+        /// Keeps count of the shares of tickets sharing numbers with `ticket` for every tracked mask.
+        fn count_matches(&mut self, ticket: &[u8], shares: u32) {
+            self.update_match_counts(ticket, |count| count + shares);
+        }
+
+        /// Applies `update` to the match counts of every tracked part of `ticket`.
+        fn update_match_counts(&mut self, ticket: &[u8], update: impl Fn(u32) -> u32) {
+            let min_matches = match self.min_tracked_matches() {
+                Some(min_matches) => min_matches,
+                None => return,
+            };
+            // the closest matches are found without the bonus number, only the tiers need it
+            let min_bonus_matches = match self.state.config.bonus {
+                Some(_) => self.min_tier_matches(),
+                None => None,
+            };
+            let shape = self.state.config.ticket;
+            for mask in 1..=full_mask(shape) {
+                let matches = mask.count_ones() as u8;
+                if matches >= min_matches {
+                    let mut bonus_masks = vec![0];
+                    if min_bonus_matches.is_some_and(|min| matches >= min) {
+                        bonus_masks.push(bonus_mask(shape));
+                    }
+                    for bonus_mask in bonus_masks.iter() {
+                        let mask = mask | bonus_mask;
+                        let key = (
+                            match_mask(mask, shape),
+                            masked(ticket, mask),
+                            self.state.round,
+                        );
+                        let count = self.match_counts.get(&key).unwrap_or(0);
+                        self.match_counts.insert(&key, &update(count));
+                    }
+                }
+            }
+        }
+
+        /// Moves the prizes of the partial-match tiers from the jackpot to the unclaimed prizes.
+        fn settle_prize_tiers(&mut self) -> Vec<TierResult> {
+            let min_matches = match self.min_tier_matches() {
+                Some(min_matches) => min_matches,
+                None => return Vec::new(),
+            };
+            let exact = self.exact_matches(min_matches, false);
+            let mut exact_with_bonus = [0; MAX_NUMBERS as usize + 1];
+            if self.state.config.bonus.is_some() {
+                exact_with_bonus = self.exact_matches(min_matches, true);
+            }
+
+            let mut results = Vec::new();
+            for tier in self.state.config.prize_tiers.clone() {
+                let k = usize::from(tier.matches);
+                let winners = if tier.bonus {
+                    exact_with_bonus[k]
+                } else {
+                    exact[k] - exact_with_bonus[k]
+                };
+                let mut prize = 0;
+                if winners > 0 {
+                    prize = prize_per_winner(tier.prize, self.jackpot, winners);
+                    let total = prize * Balance::from(winners);
+                    self.jackpot -= total;
+                    self.state.unclaimed_prizes += total;
+                }
+                results.push(TierResult {
+                    matches: tier.matches,
+                    bonus: tier.bonus,
+                    winners,
+                    prize_per_winner: prize,
+                });
+            }
+            if self.state.config.overflow == Overflow::LowerTiers {
+                if let Some(best) = results
+                    .iter_mut()
+                    .filter(|result| result.winners > 0)
+                    .max_by_key(|result| (result.matches, result.bonus))
+                {
+                    let extra = self.state.secondary_pot / Balance::from(best.winners);
+                    let total = extra * Balance::from(best.winners);
+                    best.prize_per_winner += extra;
+                    self.state.secondary_pot -= total;
+                    self.state.unclaimed_prizes += total;
+                }
+            }
+            results
+        }
+
+        /// Shares the jackpot among the tickets with the most main numbers right, it is moved
+        /// to the unclaimed prizes when the round is reset.
+        fn settle_closest_matches(&mut self) -> Option<TierResult> {
+            let exact = self.exact_matches(1, false);
+            let matches = (1..=self.state.config.ticket.numbers)
+                .rev()
+                .find(|matches| exact[usize::from(*matches)] > 0)?;
+            let winners = exact[usize::from(matches)];
+            self.top_up_jackpot();
+            let prize = self.jackpot / Balance::from(winners);
+            self.state.unclaimed_prizes += prize * Balance::from(winners);
+            self.last_pot_per_ticket = prize;
+            Some(TierResult {
+                matches,
+                bonus: false,
+                winners,
+                prize_per_winner: prize,
+            })
+        }
+
+        /// Numbers of tickets with exactly `k` main numbers right, of those with the bonus number
+        /// right as well if `with_bonus` is set.
+        ///
+        /// Derived from the match counts of the winner ticket by inclusion-exclusion, so no
+        /// ticket has to be visited.
+        fn exact_matches(
+            &self,
+            min_matches: u8,
+            with_bonus: bool,
+        ) -> [u32; MAX_NUMBERS as usize + 1] {
+            let shape = self.state.config.ticket;
+            let bonus_mask = if with_bonus { bonus_mask(shape) } else { 0 };
+            let mut exact = [0; MAX_NUMBERS as usize + 1];
+            for k in (min_matches..=shape.numbers).rev() {
+                let mut count = 0;
+                for mask in 1..=full_mask(shape) {
+                    if mask.count_ones() as u8 == k {
+                        let mask = mask | bonus_mask;
+                        count += self
+                            .match_counts
+                            .get((
+                                match_mask(mask, shape),
+                                masked(&self.state.winner_tickets[0], mask),
+                                self.state.round,
+                            ))
+                            .unwrap_or(0);
+                    }
+                }
+                for more in k + 1..=shape.numbers {
+                    count -= binomial(more, k) * exact[usize::from(more)];
+                }
+                exact[usize::from(k)] = count;
+            }
+            exact
+        }
+
+        /// Pays the holders of the box tickets matching the drawn numbers in any order.
+        fn settle_box_bets(&mut self) -> (u32, Balance) {
+            let box_bet = match self.state.config.box_bet {
+                Some(box_bet) => box_bet,
+                None => return (0, 0),
+            };
+            let ticket = sorted_main(
+                self.state.winner_tickets[0].clone(),
+                self.state.config.ticket,
+            );
+            let totals = self.ticket_totals(BetKind::Box, &ticket, self.state.round);
+            if totals.shares == 0 {
+                return (0, 0);
+            }
+            let prize = prize_per_winner(box_bet.prize, self.jackpot, totals.shares);
+            self.jackpot -= prize * Balance::from(totals.shares);
+            self.queue_payout(BetKind::Box, ticket, totals, prize);
+            (totals.shares, prize)
+        }
+
+        /// Fewest numbers right of the tickets which have to be counted.
+        fn min_tracked_matches(&self) -> Option<u8> {
+            if self.state.config.must_be_won_after.is_some() {
+                Some(1)
+            } else {
+                self.min_tier_matches()
+            }
+        }
+
+        fn min_tier_matches(&self) -> Option<u8> {
+            self.state
+                .config
+                .prize_tiers
+                .iter()
+                .map(|tier| tier.matches)
+                .min()
+        }
+
+        /// Pays the jackpot to the holders of the main winning ticket, returns the prize per
+        /// share.
+        fn transfer_to_winners(&mut self) -> Balance {
+            let totals = self.ticket_totals(
+                BetKind::Ticket,
+                &self.state.winner_tickets[0],
+                self.state.round,
+            );
+            self.top_up_jackpot();
+            if self.jackpot == 0 {
+                return 0;
+            }
+            self.last_pot_per_ticket = self.jackpot / Balance::from(totals.shares);
+            self.queue_payout(
+                BetKind::Ticket,
+                self.state.winner_tickets[0].clone(),
+                totals,
+                self.last_pot_per_ticket,
+            );
+            self.reset_game();
+            self.last_pot_per_ticket
+        }
+
+        /// Tops up a jackpot below the minimum from the reserve.
+        fn top_up_jackpot(&mut self) {
+            let top_up = self
+                .state
+                .config
+                .min_jackpot
+                .saturating_sub(self.jackpot)
+                .min(self.state.reserve);
+            if top_up > 0 {
+                self.state.reserve -= top_up;
+                self.jackpot += top_up;
+                self.env().emit_event(ReserveUsed {
+                    round: self.state.round,
+                    amount: top_up,
+                });
+            }
+        }
+
+        /// Reserves `prize` for every share of a winning ticket of the actual round.
+        fn queue_payout(
+            &mut self,
+            kind: BetKind,
+            ticket: Ticket,
+            totals: TicketTotals,
+            prize: Balance,
+        ) {
+            self.state.unclaimed_prizes += prize * Balance::from(totals.shares);
+            self.state.pending_payouts += totals.holders;
+            self.payouts.insert(
+                self.state.payout_count,
+                &Payout {
+                    kind,
+                    ticket,
+                    round: self.state.round,
+                    draw: self.state.draw_count - 1,
+                    next_holder: 0,
+                    holders: totals.holders,
+                    prize,
+                    winner: None,
+                },
+            );
+            self.state.payout_count += 1;
+        }
+
+        /// Reserves `prize` for `winner` in the actual round.
+        fn queue_account_payout(&mut self, winner: AccountId, prize: Balance) {
+            self.state.unclaimed_prizes += prize;
+            self.state.pending_payouts += 1;
+            self.payouts.insert(
+                self.state.payout_count,
+                &Payout {
+                    kind: BetKind::Raffle,
+                    ticket: Ticket::new(),
+                    round: self.state.round,
+                    draw: self.state.draw_count - 1,
+                    next_holder: 0,
+                    holders: 1,
+                    prize,
+                    winner: Some(winner),
+                },
+            );
+            self.state.payout_count += 1;
+        }
+
+        /// Pays up to `max_payouts` holders of the queued payouts, returns the number of paid holders.
+        fn pay_out(&mut self, max_payouts: u32) -> u32 {
+            let mut paid = 0;
+            while paid < max_payouts && self.state.first_payout < self.state.payout_count {
+                let mut payout = self.payouts.get(self.state.first_payout).unwrap();
+                while paid < max_payouts && payout.next_holder < payout.holders {
+                    let amount;
+                    let winner_id;
+                    if let Some(winner) = payout.winner {
+                        winner_id = winner;
+                        amount = payout.prize;
+                    } else {
+                        winner_id = self
+                            .ticket_holders
+                            .get((
+                                payout.kind,
+                                &payout.ticket,
+                                payout.round,
+                                payout.next_holder,
+                            ))
+                            .unwrap();
+                        let shares = self.holder_shares_at(
+                            payout.kind,
+                            &payout.ticket,
+                            payout.round,
+                            winner_id,
+                            payout.draw,
+                        );
+                        amount = payout.prize * Balance::from(shares);
+                    }
+                    if self.send_prize(winner_id, amount).is_ok() {
+                        self.state.unclaimed_prizes -= amount;
+                    } else {
+                        // stays claimable, the winner can withdraw it later
+                        let credit = self.credits.get(winner_id).unwrap_or(0);
+                        self.credits.insert(winner_id, &(credit + amount));
+                    }
+                    self.state.pending_payouts -= 1;
+                    payout.next_holder += 1;
+                    paid += 1;
+                }
+                if payout.next_holder == payout.holders {
+                    self.payouts.remove(self.state.first_payout);
+                    self.state.first_payout += 1;
+                } else {
+                    self.payouts.insert(self.state.first_payout, &payout);
+                }
+            }
+            paid
+        }
+
+        fn reset_game(&mut self) {
+            self.state.round += 1;
+            self.last_jackpot = self.jackpot;
+            self.jackpot = self.seeded_jackpots.get(self.state.round).unwrap_or(0);
+            if self.jackpot > 0 {
+                self.seeded_jackpots.remove(self.state.round);
+                self.state.seeded_total -= self.jackpot;
+            }
+            if self.state.config.overflow == Overflow::SecondaryPot {
+                let room = match self.state.config.jackpot_cap {
+                    Some(cap) => cap.saturating_sub(self.jackpot),
+                    None => Balance::MAX,
+                };
+                let carried = self.state.secondary_pot.min(room);
+                self.state.secondary_pot -= carried;
+                self.jackpot += carried;
+            }
+            self.state.subscriptions_entered = 0;
+        }
+
+        /// Enters up to `max_subscriptions` subscriptions which are still pending for the actual
+        /// round, anyone may call it. Returns the number entered.
+        #[ink(message)]
+        pub fn enter_subscriptions(&mut self, max_subscriptions: u32) -> u32 {
+            if self.migration_pending() {
+                return 0;
+            }
+            self.enter_pending_subscriptions(max_subscriptions.min(MAX_SUBSCRIPTIONS_PER_CALL))
+        }
+
+        /// Pays out up to `max_payouts` winning holders which are still pending, anyone may call it.
+        #[ink(message)]
+        pub fn pay_out_winners(&mut self, max_payouts: u32) -> u32 {
+            if self.migration_pending() {
+                return 0;
+            }
+            self.pay_out(max_payouts.min(MAX_PAYOUTS_PER_CALL))
+        }
+
+        /// Fills the fields added after the first release once the code of a first release
+        /// contract is upgraded, taking over its round and winner ticket with the default
+        /// configuration. Callable once and only by `LEGACY_OWNER`, which becomes the owner.
+        #[ink(message)]
+        pub fn migrate_legacy_state(&mut self) -> Result<()> {
+            if self.state.version != 0 {
+                return Err(Error::AlreadyMigrated);
+            }
+            let owner = AccountId::from(LEGACY_OWNER);
+            if self.env().caller() != owner {
+                return Err(Error::NotOwner);
+            }
+            self.state.version = STATE_VERSION;
+            self.state.owner = owner;
+            self.state.round = u32::from(self.legacy_round);
+            self.state.winner_tickets = vec![self.legacy_winner_ticket.to_vec()];
+            Ok(())
+        }
+
+        /// Moves the holders of `ticket` in `round` out of the storage layout with eight slots per
+        /// ticket, returns the number of moved shares. Anyone may call it once the state is
+        /// migrated, a migrated ticket is removed from the old layout.
+        #[ink(message)]
+        pub fn migrate_legacy_holders(&mut self, ticket: [u8; 3], round: u8) -> Result<u32> {
+            if self.migration_pending() {
+                return Err(Error::MigrationPending);
+            }
+            let legacy_key = (ticket, round);
+            let holders = match self.legacy_ticket_and_address.get(legacy_key) {
+                Some(holders) => holders,
+                None => return Ok(0),
+            };
+            let ticket = self.checked_ticket(ticket.to_vec())?;
+            let round = u32::from(round);
+            let mut shares = 0;
+            for holder in holders {
+                // the old layout marked empty slots with the default account
+                if holder == AccountId::default() {
+                    continue;
+                }
+                self.add_shares(BetKind::Ticket, &ticket, round, holder, 0, 1);
+                if round == self.state.round {
+                    self.count_matches(&ticket, 1);
+                }
+                shares += 1;
+            }
+            // every slot of the old layout was bought on its own
+            let mut stats = self.round_stats.get(round).unwrap_or_default();
+            stats.tickets_sold += shares;
+            self.round_stats.insert(round, &stats);
+            self.legacy_ticket_and_address.remove(legacy_key);
+            Ok(shares)
+        }
+
+        /// Adds the transferred value to the bankroll of the fixed-odds mode, only the owner may
+        /// call it. The owner gets pool shares like any other liquidity provider.
+        #[ink(message, payable)]
+        pub fn fund_bankroll(&mut self) -> Result<()> {
+            if self.migration_pending() {
+                return self.refund(Error::MigrationPending);
+            }
+            if self.env().caller() != self.state.owner && self.env().transferred_value() > 0 {
+                return self.refund(Error::NotOwner);
+            }
+            self.deposit_liquidity().map(|_| ())
+        }
+
+        /// Adds the transferred value to the bankroll of the fixed-odds mode, returns the pool
+        /// shares minted for it at the actual share price. Deposits too small for a single share
+        /// are refunded.
+        #[ink(message, payable)]
+        pub fn deposit_liquidity(&mut self) -> Result<Balance> {
+            if self.migration_pending() {
+                return self.refund(Error::MigrationPending);
+            }
+            let amount = self.env().transferred_value();
+            if amount == 0 {
+                return Err(Error::NoValue);
+            }
+            if self.state.config.mode != GameMode::FixedOdds {
+                return self.refund(Error::WrongMode);
+            }
+            let shares = if self.state.lp_total_shares == 0 {
+                amount
+            } else {
+                amount * self.state.lp_total_shares / self.state.bankroll.max(1)
+            };
+            if shares == 0 {
+                return self.refund(Error::InsufficientShares);
+            }
+            let provider = self.env().caller();
+            let held = self.lp_shares.get(provider).unwrap_or(0);
+            self.lp_shares.insert(provider, &(held + shares));
+            self.lp_deposit_blocks
+                .insert(provider, &self.env().block_number());
+            self.state.lp_total_shares += shares;
+            self.state.bankroll += amount;
+            self.env().emit_event(LiquidityDeposited {
+                provider,
+                amount,
+                shares,
+            });
+            Ok(shares)
+        }
+
+        /// Sends the value of `shares` pool shares of the caller out of the bankroll. Allowed
+        /// while no bet of the round is open or once the last deposit is `LP_COOLDOWN` blocks
+        /// old, the bankroll left has to cover the exposure of the round.
+        #[ink(message)]
+        pub fn withdraw_liquidity(&mut self, shares: Balance) -> Result<Balance> {
+            if self.migration_pending() {
+                return Err(Error::MigrationPending);
+            }
+            let provider = self.env().caller();
+            let held = self.lp_shares.get(provider).unwrap_or(0);
+            if shares == 0 || shares > held {
+                return Err(Error::InsufficientShares);
+            }
+            let deposited = self.lp_deposit_blocks.get(provider).unwrap_or(0);
+            if self.state.round_exposure > 0
+                && self.env().block_number() < deposited.saturating_add(LP_COOLDOWN)
+            {
+                return Err(Error::WithdrawalLocked);
+            }
+            let amount = shares * self.state.bankroll / self.state.lp_total_shares;
+            if self.state.bankroll - amount < self.state.round_exposure {
+                return Err(Error::ExposureLimit);
+            }
+            self.env()
+                .transfer(provider, amount)
+                .map_err(|_| Error::TransferFailed)?;
+            self.lp_shares.insert(provider, &(held - shares));
+            self.state.lp_total_shares -= shares;
+            self.state.bankroll -= amount;
+            self.env().emit_event(LiquidityWithdrawn {
+                provider,
+                amount,
+                shares,
+            });
+            Ok(amount)
+        }
+
+        /// Sends `amount` of the treasury to `to`, only the owner may call it.
+        #[ink(message)]
+        pub fn withdraw_treasury(&mut self, to: AccountId, amount: Balance) -> Result<()> {
+            if self.migration_pending() {
+                return Err(Error::MigrationPending);
+            }
+            if self.env().caller() != self.state.owner {
+                return Err(Error::NotOwner);
+            }
+            if amount > self.state.treasury {
+                return Err(Error::InsufficientTreasury);
+            }
+            self.env()
+                .transfer(to, amount)
+                .map_err(|_| Error::TransferFailed)?;
+            self.state.treasury -= amount;
+            self.env().emit_event(TreasuryWithdrawn { to, amount });
+            Ok(())
+        }
+
+        /// Sends the payouts which failed to transfer to the caller.
+        #[ink(message)]
+        pub fn withdraw_credit(&mut self) -> Result<Balance> {
+            if self.migration_pending() {
+                return Err(Error::MigrationPending);
+            }
+            let caller = self.env().caller();
+            let amount = self.credits.get(caller).ok_or(Error::NoCredit)?;
+            self.env()
+                .transfer(caller, amount)
+                .map_err(|_| Error::TransferFailed)?;
+            self.credits.remove(caller);
+            self.state.unclaimed_prizes -= amount;
+            Ok(amount)
+        }
+
+        /// Moves the balance which isn't kept for anything into the treasury, only the owner may
+        /// call it, returns the moved amount.
+        #[ink(message)]
+        pub fn sweep_unallocated(&mut self) -> Result<Balance> {
+            if self.migration_pending() {
+                return Err(Error::MigrationPending);
+            }
+            if self.env().caller() != self.state.owner {
+                return Err(Error::NotOwner);
+            }
+            let unallocated = self.ledger().unallocated;
+            self.state.treasury += unallocated;
+            Ok(unallocated)
+        }
+
+        /// returns the funds kept for the jackpot, the treasury, the prizes and the reserve
+        /// compared with the balance of the contract
+        #[ink(message)]
+        pub fn check_solvency(&self) -> Solvency {
+            let ledger = self.ledger();
+            let liabilities = self.liabilities();
+            let balance = self.env().balance();
+            Solvency {
+                ledger,
+                liabilities,
+                balance,
+                solvent: balance >= liabilities,
+            }
+        }
+
+        /// returns the jackpot seeded for the future round `round`
+        #[ink(message)]
+        pub fn get_seeded_jackpot(&self, round: u32) -> Balance {
+            self.seeded_jackpots.get(round).unwrap_or(0)
+        }
+
+        /// returns everything `sponsor` added to jackpots
+        #[ink(message)]
+        pub fn get_sponsor_total(&self, sponsor: AccountId) -> Balance {
+            self.sponsor_totals.get(sponsor).unwrap_or(0)
+        }
+
+        /// returns the sales beyond the jackpot cap which are not paid out yet
+        #[ink(message)]
+        pub fn get_secondary_pot(&self) -> Balance {
+            self.state.secondary_pot
+        }
+
+        /// returns the funds paying the fixed-odds winners
+        #[ink(message)]
+        pub fn get_bankroll(&self) -> Balance {
+            self.state.bankroll
+        }
+
+        /// returns the payouts of all fixed-odds bets of the actual round if they won
+        #[ink(message)]
+        pub fn get_round_exposure(&self) -> Balance {
+            self.state.round_exposure
+        }
+
+        /// returns the bankroll with the shares of the liquidity providers and the exposure
+        #[ink(message)]
+        pub fn get_pool(&self) -> Pool {
+            Pool {
+                bankroll: self.state.bankroll,
+                total_shares: self.state.lp_total_shares,
+                share_price: (self.state.bankroll * SHARE_PRICE_UNIT)
+                    .checked_div(self.state.lp_total_shares)
+                    .unwrap_or(SHARE_PRICE_UNIT),
+                exposure: self.state.round_exposure,
+                free: self
+                    .state
+                    .bankroll
+                    .saturating_sub(self.state.round_exposure),
+            }
+        }
+
+        /// returns the balance of `account` which purchases draw from
+        #[ink(message)]
+        pub fn get_balance(&self, account: AccountId) -> Balance {
+            self.balances.get(account).unwrap_or(0)
+        }
+
+        /// returns whether the prizes of `account` are credited to its balance
+        #[ink(message)]
+        pub fn get_prizes_to_balance(&self, account: AccountId) -> bool {
+            self.prizes_to_balance.contains(account)
+        }
+
+        /// returns the active subscriptions of `account`
+        #[ink(message)]
+        pub fn get_subscriptions(&self, account: AccountId) -> Vec<Subscription> {
+            self.account_subscriptions
+                .get(account)
+                .unwrap_or_default()
+                .into_iter()
+                .filter_map(|id| self.subscriptions.get(id))
+                .collect()
+        }
+
+        /// returns the principal `account` deposited in the no-loss mode
+        #[ink(message)]
+        pub fn get_principal(&self, account: AccountId) -> Balance {
+            self.depositors
+                .get(account)
+                .map_or(0, |depositor| depositor.principal)
+        }
+
+        /// returns the weight of `account` in the next no-loss drawing up to now
+        #[ink(message)]
+        pub fn get_weight(&self, account: AccountId) -> Balance {
+            self.depositors.get(account).map_or(0, |depositor| {
+                Self::weight_at(
+                    &depositor,
+                    self.state.draw_count,
+                    self.last_drawing,
+                    self.env().block_number(),
+                )
+            })
+        }
+
+        /// returns the pool shares of `provider`
+        #[ink(message)]
+        pub fn get_lp_shares(&self, provider: AccountId) -> Balance {
+            self.lp_shares.get(provider).unwrap_or(0)
+        }
+
+        /// returns the pot paid to a player of the round when nobody wins the jackpot
+        #[ink(message)]
+        pub fn get_second_chance_pot(&self) -> Balance {
+            self.state.second_chance_pot
+        }
+
+        /// returns the accounts holding tickets in `round` in the order of their first
+        /// purchase, `count` players starting at player `from`
+        #[ink(message)]
+        pub fn get_round_players(&self, round: u32, from: u32, count: u32) -> Vec<AccountId> {
+            let to = self
+                .round_stats
+                .get(round)
+                .unwrap_or_default()
+                .unique_players
+                .min(from.saturating_add(count.min(MAX_PAGE_SIZE)));
+            (from..to)
+                .filter_map(|index| self.round_players.get((round, index)))
+                .collect()
+        }
+
+        /// returns the reserve which tops up jackpots below the minimum
+        #[ink(message)]
+        pub fn get_reserve(&self) -> Balance {
+            self.state.reserve
+        }
+
+        /// returns the fees in the treasury which the owner may withdraw
+        #[ink(message)]
+        pub fn get_treasury(&self) -> Balance {
+            self.state.treasury
+        }
+
+        /// returns the owner of the lottery
+        #[ink(message)]
+        pub fn get_owner(&self) -> AccountId {
+            self.state.owner
+        }
+
+        /// returns the number of winning holders which are not paid out yet
+        #[ink(message)]
+        pub fn get_pending_payouts(&self) -> u32 {
+            self.state.pending_payouts
+        }
+
+        /// returns the number of active subscriptions which are not entered for the actual round
+        /// yet
+        #[ink(message)]
+        pub fn get_pending_subscriptions(&self) -> u32 {
+            self.state.active_subscription_count - self.state.subscriptions_entered
+        }
+
+        /// returns the main winner ticket
+        #[ink(message)]
+        pub fn get_winner_ticket(&self) -> Ticket {
+            self.state
+                .winner_tickets
+                .first()
+                .cloned()
+                .unwrap_or_default()
+        }
+
+        /// returns all winner tickets of the last drawing, the main one first
+        #[ink(message)]
+        pub fn get_winner_tickets(&self) -> Vec<Ticket> {
+            self.state.winner_tickets.clone()
+        }
+        /// returns the accounts per ticket for the actual run, `count` holders starting at holder `from`
+        #[ink(message)]
+        pub fn get_accounts_by_ticket(
+            &self,
+            ticket_hash: Ticket,
+            from: u32,
+            count: u32,
+        ) -> Vec<AccountId> {
+            match self.checked_ticket(ticket_hash) {
+                Ok(ticket) => self.holders(BetKind::Ticket, &ticket, self.state.round, from, count),
+                Err(_) => Vec::new(),
+            }
+        }
+
+        /// returns the number of accounts holding a ticket for the actual run
+        #[ink(message)]
+        pub fn get_holder_count(&self, ticket: Ticket) -> u32 {
+            match self.checked_ticket(ticket) {
+                Ok(ticket) => {
+                    self.ticket_totals(BetKind::Ticket, &ticket, self.state.round)
+                        .holders
+                }
+                Err(_) => 0,
+            }
+        }
+
+        /// returns the number of shares sold of a ticket for the actual run
+        #[ink(message)]
+        pub fn get_share_count(&self, ticket: Ticket) -> u32 {
+            match self.checked_ticket(ticket) {
+                Ok(ticket) => {
+                    self.ticket_totals(BetKind::Ticket, &ticket, self.state.round)
+                        .shares
+                }
+                Err(_) => 0,
+            }
+        }
+
+        /// returns the accounts per box ticket for the actual run, `count` holders starting at
+        /// holder `from`, the order of the numbers doesn't matter
+        #[ink(message)]
+        pub fn get_accounts_by_box_ticket(
+            &self,
+            ticket: Ticket,
+            from: u32,
+            count: u32,
+        ) -> Vec<AccountId> {
+            match self.checked_ticket(ticket) {
+                Ok(ticket) => {
+                    let ticket = sorted_main(ticket, self.state.config.ticket);
+                    self.holders(BetKind::Box, &ticket, self.state.round, from, count)
+                }
+                Err(_) => Vec::new(),
+            }
+        }
+
+        /// returns the state of the lottery with the prizes the caller can still claim
+        #[ink(message)]
+        pub fn get_state(&self) -> LotteryState {
+            let phase = if self.draw_is_due() {
+                Phase::DrawDue
+            } else {
+                Phase::Open
+            };
+            LotteryState {
+                round: self.state.round,
+                phase,
+                config: self.state.config.clone(),
+                jackpot: self.jackpot,
+                last_jackpot: self.last_jackpot,
+                last_pot_per_ticket: self.last_pot_per_ticket,
+                treasury: self.state.treasury,
+                reserve: self.state.reserve,
+                secondary_pot: self.state.secondary_pot,
+                second_chance_pot: self.state.second_chance_pot,
+                bankroll: self.state.bankroll,
+                round_exposure: self.state.round_exposure,
+                total_principal: self.state.total_principal,
+                unclaimed_prizes: self.state.unclaimed_prizes,
+                pending_payouts: self.state.pending_payouts,
+                balance: self.env().balance(),
+                draw_count: self.state.draw_count,
+                last_drawing: self.last_drawing,
+                next_drawing: self.get_next_drawing(),
+                claimable: self.claimable(self.env().caller()),
+                last_result: self
+                    .state
+                    .draw_count
+                    .checked_sub(1)
+                    .and_then(|draw| self.draw_results.get(draw)),
+            }
+        }
+
+        /// returns the failed payouts and the tier prizes of the round of the last drawing
+        /// `account` can still withdraw or claim
+        #[ink(message)]
+        pub fn get_claimable(&self, account: AccountId) -> Balance {
+            self.claimable(account)
+        }
+
+        /// returns the sales of `round` over ticket and box bets
+        #[ink(message)]
+        pub fn get_round_stats(&self, round: u32) -> RoundStats {
+            let mut stats = self.round_stats.get(round).unwrap_or_default();
+            stats.shares_per_ticket = stats
+                .shares_sold
+                .checked_div(stats.distinct_combinations)
+                .unwrap_or(0);
+            stats
+        }
+
+        /// returns the tickets `account` holds in `round`, `count` tickets starting at ticket `from`
+        #[ink(message)]
+        pub fn get_tickets_of(
+            &self,
+            account: AccountId,
+            round: u32,
+            from: u32,
+            count: u32,
+        ) -> Vec<AccountTicket> {
+            let to = self
+                .account_round_ticket_count
+                .get((account, round))
+                .unwrap_or(0)
+                .min(from.saturating_add(count.min(MAX_PAGE_SIZE)));
+            (from..to)
+                .filter_map(|index| self.account_round_tickets.get((account, round, index)))
+                .filter_map(|position| self.account_ticket(account, position))
+                .collect()
+        }
+
+        /// returns the tickets of `account` over all rounds in the order they were first bought,
+        /// `count` tickets starting at ticket `from`
+        #[ink(message)]
+        pub fn get_ticket_history(
+            &self,
+            account: AccountId,
+            from: u32,
+            count: u32,
+        ) -> Vec<AccountTicket> {
+            let to = self
+                .account_ticket_count
+                .get(account)
+                .unwrap_or(0)
+                .min(from.saturating_add(count.min(MAX_PAGE_SIZE)));
+            (from..to)
+                .filter_map(|position| self.account_ticket(account, position))
+                .collect()
+        }
+
+        /// returns the number of accounts holding a box ticket for the actual run
+        #[ink(message)]
+        pub fn get_box_holder_count(&self, ticket: Ticket) -> u32 {
+            match self.checked_ticket(ticket) {
+                Ok(ticket) => {
+                    let ticket = sorted_main(ticket, self.state.config.ticket);
+                    self.ticket_totals(BetKind::Box, &ticket, self.state.round)
+                        .holders
+                }
+                Err(_) => 0,
+            }
+        }
+
+        /// returns the actual jackpot
+        #[ink(message)]
+        pub fn get_jackpot(&self) -> Balance {
+            self.jackpot
+        }
+
+        /// returns the prizes which are won but not claimed or paid out yet
+        #[ink(message)]
+        pub fn get_unclaimed_prizes(&self) -> Balance {
+            self.state.unclaimed_prizes
+        }
+
+        /// returns the settings of the game
+        #[ink(message)]
+        pub fn get_config(&self) -> LotteryConfig {
+            self.state.config.clone()
+        }
+
+        /// returns the number of drawings so far
+        #[ink(message)]
+        pub fn get_draw_count(&self) -> u32 {
+            self.state.draw_count
+        }
+
+        /// returns the result of a drawing including the results of all prize tiers
+        #[ink(message)]
+        pub fn get_draw_result(&self, draw: u32) -> Option<DrawResult> {
+            self.draw_results.get(draw)
+        }
+
+        /// returns the block of the last drawing
+        #[ink(message)]
+        pub fn get_last_drawing(&self) -> BlockNumber {
+            self.last_drawing
+        }
+
+        /// returns the end jackpot of the last round
+        #[ink(message)]
+        pub fn get_last_jackpot(&self) -> Balance {
+            self.last_jackpot
+        }
+
+        /// returns the last winners, `count` holders starting at holder `from`
+        #[ink(message)]
+        pub fn get_last_winners(&self, from: u32, count: u32) -> Vec<AccountId> {
+            if matches!(self.state.config.mode, GameMode::Raffle | GameMode::NoLoss) {
+                self.state
+                    .last_raffle_winner
+                    .into_iter()
+                    .skip(from as usize)
+                    .take(count as usize)
+                    .collect()
+            } else if self.state.round == 0 {
+                Vec::new()
+            } else {
+                self.holders(
+                    BetKind::Ticket,
+                    &self.state.winner_tickets[0],
+                    self.state.round - 1,
+                    from,
+                    count,
+                )
+            }
+        }
+
+        /// returns the block of the last drawing
+        #[ink(message)]
+        pub fn get_next_drawing(&self) -> BlockNumber {
+            self.last_drawing + BLOCKS_PER_ROUND
+        }
+
+        /// returns the price per winner of the last round
+        #[ink(message)]
+        pub fn get_last_pot_per_ticket(&self) -> Balance {
+            self.last_pot_per_ticket
+        }
+    }
+
+    /// Prize per winner of a tier, never more than the jackpot in total.
+    fn prize_per_winner(prize: Prize, jackpot: Balance, winners: u32) -> Balance {
+        match prize {
+            Prize::Share(basis_points) => {
+                jackpot * Balance::from(basis_points) / BASIS_POINTS / Balance::from(winners)
+            }
+            Prize::Fixed(amount) => amount.min(jackpot / Balance::from(winners)),
+        }
+    }
+
+    /// Sorts the main numbers, the bonus number stays last.
+    fn sorted_main(mut ticket: Ticket, shape: TicketShape) -> Ticket {
+        ticket[..usize::from(shape.numbers)].sort_unstable();
+        ticket
+    }
+
+    fn ticket_len(config: &LotteryConfig) -> usize {
+        usize::from(config.ticket.numbers) + usize::from(config.bonus.is_some())
+    }
+
+    /// Stream of random numbers out of a seed which is rehashed once all of its bytes are used.
+    struct RandomNumbers {
+        seed: [u8; 32],
+        index: usize,
+    }
+
+    impl RandomNumbers {
+        fn new(seed: [u8; 32]) -> Self {
+            Self { seed, index: 0 }
+        }
+
+        /// Every byte is a pick, bytes which would favor some numbers are skipped.
+        fn next(&mut self, min: u8, max: u8) -> u8 {
+            let range = u16::from(max - min) + 1;
+            let limit = 256 - 256 % range;
+            loop {
+                let byte = u16::from(self.next_byte());
+                if byte < limit {
+                    return min + (byte % range) as u8;
+                }
+            }
+        }
+
+        /// Every four bytes are a pick below `bound`, picks which would favor some values are
+        /// skipped.
+        fn next_below(&mut self, bound: u32) -> u32 {
+            let range = u64::from(u32::MAX) + 1;
+            let limit = range - range % u64::from(bound);
+            loop {
+                let value = u32::from_le_bytes([
+                    self.next_byte(),
+                    self.next_byte(),
+                    self.next_byte(),
+                    self.next_byte(),
+                ]);
+                if u64::from(value) < limit {
+                    return value % bound;
+                }
+            }
+        }
+
+        /// Every sixteen bytes are a pick below `bound`, picks which would favor some values
+        /// are skipped.
+        fn next_below_wide(&mut self, bound: u128) -> u128 {
+            let limit = u128::MAX - u128::MAX % bound;
+            loop {
+                let mut bytes = [0; 16];
+                bytes.iter_mut().for_each(|byte| *byte = self.next_byte());
+                let value = u128::from_le_bytes(bytes);
+                if value < limit {
+                    return value % bound;
+                }
+            }
+        }
+
+        /// Rehashes the seed once all of its bytes are used.
+        fn next_byte(&mut self) -> u8 {
+            if self.index == self.seed.len() {
+                let mut rehashed = [0; 32];
+                ink_env::hash_bytes::<ink_env::hash::Blake2x256>(&self.seed, &mut rehashed);
+                self.seed = rehashed;
+                self.index = 0;
+            }
+            self.index += 1;
+            self.seed[self.index - 1]
+        }
+    }
+
+    /// Derives a separate random stream out of `seed` for the draw named by `tag`, so its
+    /// picks don't follow the winning numbers.
+    fn tagged_seed(seed: [u8; 32], tag: &[u8]) -> [u8; 32] {
+        let mut input = seed.to_vec();
+        input.extend_from_slice(tag);
+        let mut tagged = [0; 32];
+        ink_env::hash_bytes::<ink_env::hash::Blake2x256>(&input, &mut tagged);
+        tagged
+    }
+
+    /// Draws the main winning ticket and the distinct runner-up tickets out of the random
+    /// `seed`, combinations drawn before are drawn again.
+    fn draw_winner_tickets(config: &LotteryConfig, seed: [u8; 32]) -> Vec<Ticket> {
+        let mut random = RandomNumbers::new(seed);
+        let mut tickets: Vec<Ticket> = Vec::new();
+        while tickets.len() <= config.runner_ups.len() {
+            let ticket = draw_numbers(config, &mut random);
+            if !tickets.contains(&ticket) {
+                tickets.push(ticket);
+            }
+        }
+        tickets
+    }
+
+    /// Draws a valid combination for the game, numbers which may only be picked once are
+    /// drawn again. The bonus number is drawn after the main numbers.
+    fn draw_numbers(config: &LotteryConfig, random: &mut RandomNumbers) -> Ticket {
+        let shape = config.ticket;
+        let mut numbers = Vec::new();
+        while numbers.len() < usize::from(shape.numbers) {
+            let number = random.next(shape.min, shape.max);
+            if shape.duplicates || !numbers.contains(&number) {
+                numbers.push(number);
+            }
+        }
+        if !shape.ordered {
+            numbers.sort_unstable();
+        }
+        if let Some(bonus) = config.bonus {
+            numbers.push(random.next(bonus.min, bonus.max));
+        }
+        numbers
+    }
+
+    fn full_mask(shape: TicketShape) -> u16 {
+        (1 << shape.numbers) - 1
+    }
+
+    /// Picks the bonus number which follows the main numbers.
+    fn bonus_mask(shape: TicketShape) -> u16 {
+        1 << shape.numbers
+    }
+
+    /// Positions only matter for ordered games, unordered tickets are matched by their numbers.
+    fn match_mask(mask: u16, shape: TicketShape) -> u16 {
+        if shape.ordered {
+            mask
+        } else {
+            mask & !full_mask(shape)
+        }
+    }
+
+    /// Keeps the numbers of `ticket` on the positions set in `mask`.
+    fn masked(ticket: &[u8], mask: u16) -> Ticket {
+        ticket
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| mask & (1 << i) != 0)
+            .map(|(_, number)| *number)
+            .collect()
+    }
+
+    /// Counts the main numbers of `ticket` which are right, in position for ordered games.
+    fn matching_numbers(ticket: &[u8], winner_ticket: &[u8], shape: TicketShape) -> u8 {
+        let ticket = &ticket[..usize::from(shape.numbers)];
+        let winner_ticket = &winner_ticket[..usize::from(shape.numbers)];
+        if shape.ordered {
+            ticket
+                .iter()
+                .zip(winner_ticket.iter())
+                .filter(|(number, winner)| number == winner)
+                .count() as u8
+        } else {
+            ticket
+                .iter()
+                .filter(|number| winner_ticket.contains(number))
+                .count() as u8
+        }
+    }
+
+    fn binomial(n: u8, k: u8) -> u32 {
+        (0..u32::from(k)).fold(1, |acc, i| acc * (u32::from(n) - i) / (i + 1))
+    }
+
+    /// Number of distinct tickets of the game including the bonus number.
+    fn combinations(config: &LotteryConfig) -> u128 {
+        let shape = config.ticket;
+        let range = u128::from(shape.max - shape.min) + 1;
+        let numbers = u128::from(shape.numbers);
+        let choose = |n: u128| (0..numbers).fold(1, |acc, i| acc * (n - i) / (i + 1));
+        let main = match (shape.ordered, shape.duplicates) {
+            (true, true) => range.pow(u32::from(shape.numbers)),
+            (true, false) => (range + 1 - numbers..=range).product(),
+            (false, false) => choose(range),
+            (false, true) => choose(range + numbers - 1),
+        };
+        let bonus = config
+            .bonus
+            .map_or(1, |bonus| u128::from(bonus.max - bonus.min) + 1);
+        main * bonus
+    }
+
+    /// Unit tests in Rust are normally defined within such a `#[cfg(test)]`
+    /// module and test functions are marked with a `#[test]` attribute.
     /// The below code is technically just normal Rust code.
     #[cfg(test)]
     mod tests {
         /// Imports all the definitions from the outer scope so we can use them here.
         use super::*;
 
-        use crate::CustomEnvironment;
-        /// Imports `ink_lang` so we can use `#[ink::test]`.
-        use ink_lang as ink;
+        use crate::CustomEnvironment;
+        /// Imports `ink_lang` so we can use `#[ink::test]`.
+        use ink_lang as ink;
+        use ink_primitives::Key;
+        use ink_storage::traits::{pull_spread_root, push_spread_root};
+
+        fn default_accounts() -> ink_env::test::DefaultAccounts<CustomEnvironment> {
+            ink_env::test::default_accounts::<Environment>()
+        }
+
+        fn set_next_caller(caller: AccountId) {
+            ink_env::test::set_caller::<Environment>(caller);
+            ink_env::test::set_account_balance::<Environment>(caller, BET_PRICE * 255);
+            ink_env::test::set_value_transferred::<Environment>(BET_PRICE);
+        }
+
+        fn set_next_caller_too_low_balance(caller: AccountId) {
+            let too_low_price = BET_PRICE - 1;
+            ink_env::test::set_caller::<Environment>(caller);
+            ink_env::test::set_value_transferred::<Environment>(too_low_price);
+        }
+
+        fn set_next_caller_too_high_balance(caller: AccountId) {
+            let too_high_price = BET_PRICE + 1;
+            ink_env::test::set_caller::<Environment>(caller);
+            ink_env::test::set_value_transferred::<Environment>(too_high_price);
+        }
+
+        fn register_number_of_same_tickets(
+            num_registers: u8,
+            ticket: [u8; 3],
+            mut contract: Lottery,
+        ) -> Lottery {
+            for _i in 0..num_registers {
+                assert_eq!(contract.register_ticket(ticket.to_vec(), 1), Ok(()));
+            }
+            contract
+        }
+
+        fn get_win_ticket() -> Ticket {
+            vec![21, 236, 123]
+        }
+
+        fn get_win_ticket_chain_extension() -> [u8; 32] {
+            let mut ticket_arr = [0; 32];
+            ticket_arr[0] = 21;
+            ticket_arr[1] = 236;
+            ticket_arr[2] = 123;
+
+            ticket_arr
+        }
+
+        fn setup_jackpot(numb_tickets: u8) -> Lottery {
+            use_random_chain_extension();
+            let mut contract = Lottery::new();
+            let default_accounts = default_accounts();
+            let mut ticket_arr = [0; 3];
+            set_next_caller(default_accounts.bob);
+            for i in 0..numb_tickets {
+                ticket_arr[0] = i;
+                ticket_arr[1] = i;
+                ticket_arr[2] = i;
+                assert_eq!(
+                    ink_env::pay_with_call!(
+                        contract.register_ticket(ticket_arr.to_vec(), 1),
+                        BET_PRICE
+                    ),
+                    Ok(())
+                );
+            }
+            contract
+        }
+
+        fn register_number_of_win_tickets(num_registers: u8, mut contract: Lottery) -> Lottery {
+            let default_accounts = default_accounts();
+
+            for i in 0..num_registers {
+                let acc = i % 3;
+                if acc == 0 {
+                    set_next_caller(default_accounts.eve);
+                } else if acc == 1 {
+                    set_next_caller(default_accounts.alice);
+                } else {
+                    set_next_caller(default_accounts.bob);
+                }
+                assert_eq!(
+                    ink_env::pay_with_call!(
+                        contract.register_ticket(get_win_ticket(), 1),
+                        BET_PRICE
+                    ),
+                    Ok(())
+                );
+            }
+            contract
+        }
+
+        fn use_random_chain_extension() {
+            struct MockedExtension;
+            impl ink_env::test::ChainExtension for MockedExtension {
+                fn func_id(&self) -> u32 {
+                    1101
+                }
+                fn call(&mut self, _input: &[u8], output: &mut Vec<u8>) -> u32 {
+                    let ret = get_win_ticket_chain_extension();
+                    scale::Encode::encode_to(&ret, output);
+                    0
+                }
+            }
+            ink_env::test::register_chain_extension(MockedExtension);
+        }
+
+        fn advance_blocks(numb_blocks: u32) {
+            for _i in 0..numb_blocks {
+                ink_env::test::advance_block::<Environment>();
+            }
+        }
+
+        #[ink::test]
+        fn default_works() {
+            use_random_chain_extension();
+            let mut contract = Lottery::new();
+            let init = contract.get_winner_ticket();
+            contract.draw();
+            let second = contract.get_winner_ticket();
+            assert_ne!(init, second);
+        }
+
+        /// We test if the default constructor does its job.
+        #[ink::test]
+        fn register_works() {
+            let default_accounts = default_accounts();
+            let mut ticket = [0; 3];
+            ticket[0] = 1;
+            ticket[1] = 2;
+            ticket[2] = 3;
+            set_next_caller(default_accounts.alice);
+            let mut contract = Lottery::new();
+
+            assert_eq!(contract.register_ticket(ticket.to_vec(), 1), Ok(()));
+        }
+
+        #[ink::test]
+        fn transferred_balance_too_low() {
+            fund_contract();
+            let default_accounts = default_accounts();
+            set_next_caller_too_low_balance(default_accounts.alice);
+            let ticket_arr = [0; 3];
+            let mut contract = Lottery::new();
+            assert_eq!(
+                contract.register_ticket(ticket_arr.to_vec(), 1),
+                Err(Error::TicketCosts)
+            );
+            assert_eq!(0, contract.get_jackpot());
+        }
+
+        #[ink::test]
+        fn transferred_balance_too_high() {
+            fund_contract();
+            let default_accounts = default_accounts();
+            set_next_caller(default_accounts.bob);
+            set_next_caller_too_high_balance(default_accounts.bob);
+            let bob_balance =
+                ink_env::test::get_account_balance::<Environment>(default_accounts.bob).unwrap();
+            let ticket_arr = [0; 3];
+            let mut contract = Lottery::new();
+            assert_eq!(contract.register_ticket(ticket_arr.to_vec(), 1), Ok(()));
+            assert_eq!(
+                bob_balance + 1,
+                ink_env::test::get_account_balance::<Environment>(default_accounts.bob).unwrap()
+            );
+            assert_eq!(0, contract.get_balance(default_accounts.bob));
+            assert_eq!(BET_PRICE, contract.get_jackpot());
+        }
+
+        #[ink::test]
+        fn overpayment_is_credited_for_later_purchases() {
+            fund_contract();
+            let default_accounts = default_accounts();
+            let mut contract = Lottery::with_config(LotteryConfig {
+                overpayment: Overpayment::Credit,
+                ..Default::default()
+            });
+            set_next_caller(default_accounts.bob);
+            ink_env::test::set_value_transferred::<Environment>(BET_PRICE + 600_000);
+            assert_eq!(contract.register_ticket(get_win_ticket(), 1), Ok(()));
+            assert_eq!(600_000, contract.get_balance(default_accounts.bob));
+
+            ink_env::test::set_value_transferred::<Environment>(500_000);
+            assert_eq!(contract.register_ticket(get_win_ticket(), 1), Ok(()));
+            assert_eq!(100_000, contract.get_balance(default_accounts.bob));
+            ink_env::test::set_value_transferred::<Environment>(0);
+            assert_eq!(
+                contract.register_ticket(get_win_ticket(), 1),
+                Err(Error::TicketCosts)
+            );
+            assert_eq!(2, contract.get_share_count(get_win_ticket()));
+            assert_eq!(100_000, contract.check_solvency().ledger.balances);
+        }
+
+        #[ink::test]
+        fn get_accounts_by_lottery_default_should_be_default() {
+            let default_accounts = default_accounts();
+            set_next_caller(default_accounts.alice);
+            let contract = Lottery::default();
+            assert_eq!(
+                contract.get_accounts_by_ticket(vec![0; 3], 0, 8),
+                Vec::<AccountId>::new()
+            );
+        }
+
+        #[ink::test]
+        fn get_accounts_by_ticket_init_should_be_default() {
+            let default_accounts = default_accounts();
+            set_next_caller(default_accounts.alice);
+            let contract = Lottery::new();
+            assert_eq!(
+                contract.get_accounts_by_ticket(vec![0; 3], 0, 8),
+                Vec::<AccountId>::new()
+            );
+        }
+
+        #[ink::test]
+        fn get_next_drawing_init_should_be_block_per_round() {
+            let contract = Lottery::new();
+            assert_eq!(BLOCKS_PER_ROUND, contract.get_next_drawing())
+        }
+
+        #[ink::test]
+        fn next_drawing_changed_after_first_draw() {
+            let mut contract = setup_jackpot(8);
+            let default_accounts = default_accounts();
+            let ticket_arr = [0; 3];
+            let old_next_drawing = contract.get_next_drawing();
+            advance_blocks(BLOCKS_PER_ROUND);
+            set_next_caller(default_accounts.bob);
+            assert_eq!(contract.register_ticket(ticket_arr.to_vec(), 1), Ok(()));
+            assert_ne!(old_next_drawing, contract.get_next_drawing());
+        }
+
+        #[ink::test]
+        fn last_winner_ticket_changed_after_first_draw() {
+            let mut contract = setup_jackpot(8);
+            let default_accounts = default_accounts();
+            let ticket_arr = [0; 3];
+            let old_win_ticket = contract.get_winner_ticket();
+            advance_blocks(BLOCKS_PER_ROUND);
+            set_next_caller(default_accounts.bob);
+            assert_eq!(contract.register_ticket(ticket_arr.to_vec(), 1), Ok(()));
+            assert_ne!(get_win_ticket(), old_win_ticket)
+        }
+
+        #[ink::test]
+        fn last_drawing_changed_after_first_draw() {
+            let mut contract = setup_jackpot(8);
+            let default_accounts = default_accounts();
+            let ticket_arr = [0; 3];
+            let old_last_drawing = contract.get_last_drawing();
+            advance_blocks(BLOCKS_PER_ROUND);
+            set_next_caller(default_accounts.bob);
+            assert_eq!(contract.register_ticket(ticket_arr.to_vec(), 1), Ok(()));
+            assert_ne!(old_last_drawing, contract.get_last_drawing());
+        }
+
+        #[ink::test]
+        fn get_accounts_by_ticket_should_be_alice() {
+            let default_accounts = default_accounts();
+            set_next_caller(default_accounts.alice);
+            let mut contract = Lottery::new();
+
+            let mut ticket = [0; 3];
+            ticket[0] = 1;
+            ticket[1] = 2;
+            ticket[2] = 3;
+            contract = register_number_of_same_tickets(1, ticket, contract);
+            let winner_acc = vec![default_accounts.alice];
+            assert_eq!(
+                contract.get_accounts_by_ticket(ticket.to_vec(), 0, 8),
+                winner_acc
+            );
+        }
+
+        #[ink::test]
+        fn get_accounts_by_ticket_should_be_alice_once() {
+            let default_accounts = default_accounts();
+            let mut ticket = [0; 3];
+            ticket[0] = 1;
+            ticket[1] = 2;
+            ticket[2] = 3;
+            let winner_acc = vec![default_accounts.alice];
+
+            set_next_caller(default_accounts.alice);
+            let mut contract = Lottery::new();
+            contract = register_number_of_same_tickets(2, ticket, contract);
+
+            assert_eq!(
+                contract.get_accounts_by_ticket(ticket.to_vec(), 0, 8),
+                winner_acc
+            );
+            assert_eq!(contract.get_share_count(ticket.to_vec()), 2);
+        }
+
+        #[ink::test]
+        fn ticket_filled_dont_panic() {
+            let default_accounts = default_accounts();
+            let mut ticket_arr = [0; 3];
+            ticket_arr[0] = 100;
+            ticket_arr[1] = 100;
+            ticket_arr[2] = 100;
+
+            set_next_caller(default_accounts.bob);
+            let mut contract = Lottery::new();
+
+            // 8 is fine
+            for _i in 0..8 {
+                assert_eq!(contract.register_ticket(ticket_arr.to_vec(), 1), Ok(()));
+            }
+        }
+
+        #[ink::test]
+        fn ticket_never_sold_out() {
+            let default_accounts = default_accounts();
+            let mut ticket_arr = [0; 3];
+            ticket_arr[0] = 99;
+            ticket_arr[1] = 99;
+            ticket_arr[2] = 99;
+
+            set_next_caller(default_accounts.bob);
+            let mut contract = Lottery::new();
+
+            for _i in 0..20 {
+                assert_eq!(contract.register_ticket(ticket_arr.to_vec(), 1), Ok(()));
+            }
+            assert_eq!(contract.get_holder_count(ticket_arr.to_vec()), 1);
+            assert_eq!(contract.get_share_count(ticket_arr.to_vec()), 20);
+        }
+
+        #[ink::test]
+        fn holders_are_paginated() {
+            let default_accounts = default_accounts();
+            let mut contract = Lottery::new();
+            for caller in [
+                default_accounts.alice,
+                default_accounts.bob,
+                default_accounts.charlie,
+                default_accounts.django,
+            ] {
+                set_next_caller(caller);
+                assert_eq!(contract.register_ticket(get_win_ticket(), 1), Ok(()));
+            }
+            assert_eq!(
+                contract.get_accounts_by_ticket(get_win_ticket(), 1, 2),
+                vec![default_accounts.bob, default_accounts.charlie]
+            );
+            assert_eq!(
+                contract.get_accounts_by_ticket(get_win_ticket(), 3, 8),
+                vec![default_accounts.django]
+            );
+            assert_eq!(
+                contract.get_accounts_by_ticket(get_win_ticket(), 4, 8),
+                Vec::<AccountId>::new()
+            );
+        }
+
+        #[ink::test]
+        fn zero_account_is_a_holder() {
+            let mut contract = Lottery::new();
+            set_next_caller(AccountId::default());
+            assert_eq!(contract.register_ticket(get_win_ticket(), 1), Ok(()));
+            assert_eq!(
+                contract.get_accounts_by_ticket(get_win_ticket(), 0, 8),
+                vec![AccountId::default()]
+            );
+        }
+
+        #[ink::test]
+        fn many_winners_are_paid_in_batches() {
+            use_random_chain_extension();
+            let mut contract = Lottery::new();
+            for i in 0..20 {
+                set_next_caller(AccountId::from([i + 1; 32]));
+                assert_eq!(contract.register_ticket(get_win_ticket(), 1), Ok(()));
+            }
+            contract.draw();
+
+            assert_eq!(1_000_000, contract.get_last_pot_per_ticket());
+            assert_eq!(20, contract.get_last_winners(0, 100).len());
+            assert_eq!(12, contract.get_pending_payouts());
+            assert_eq!(12_000_000, contract.get_unclaimed_prizes());
+            assert_eq!(contract.pay_out_winners(10), 10);
+            assert_eq!(contract.pay_out_winners(10), 2);
+            assert_eq!(contract.pay_out_winners(10), 0);
+            assert_eq!(0, contract.get_pending_payouts());
+            assert_eq!(0, contract.get_unclaimed_prizes());
+        }
+
+        #[ink::test]
+        fn holders_are_paid_by_their_shares() {
+            use_random_chain_extension();
+            fund_contract();
+            let default_accounts = default_accounts();
+            let mut contract = Lottery::new();
+            set_next_caller(default_accounts.charlie);
+            contract = register_number_of_same_tickets(2, [21, 236, 123], contract);
+            set_next_caller(default_accounts.bob);
+            contract = register_number_of_same_tickets(1, [21, 236, 123], contract);
+            let charlie_balance =
+                ink_env::test::get_account_balance::<Environment>(default_accounts.charlie)
+                    .unwrap();
+            contract.draw();
+
+            assert_eq!(
+                3,
+                contract.get_draw_result(0).unwrap().winning_tickets[0].winners
+            );
+            assert_eq!(
+                vec![default_accounts.charlie, default_accounts.bob],
+                contract.get_last_winners(0, 8)
+            );
+            assert_eq!(
+                charlie_balance + 2_000_000,
+                ink_env::test::get_account_balance::<Environment>(default_accounts.charlie)
+                    .unwrap()
+            );
+            assert_eq!(0, contract.get_unclaimed_prizes());
+        }
+
+        #[ink::test]
+        fn register_many_shares_at_once() {
+            let default_accounts = default_accounts();
+            let mut contract = Lottery::new();
+            set_next_caller(default_accounts.alice);
+            assert_eq!(
+                ink_env::pay_with_call!(
+                    contract.register_ticket(get_win_ticket(), 3),
+                    BET_PRICE * 3
+                ),
+                Ok(())
+            );
+            assert_eq!(
+                contract.get_accounts_by_ticket(get_win_ticket(), 0, 8),
+                vec![default_accounts.alice]
+            );
+            assert_eq!(contract.get_share_count(get_win_ticket()), 3);
+            assert_eq!(BET_PRICE * 3, contract.get_jackpot());
+        }
+
+        #[ink::test]
+        fn purchases_without_value_use_deposit() {
+            fund_contract();
+            let default_accounts = default_accounts();
+            let mut contract = Lottery::with_config(box_config());
+            set_next_caller(default_accounts.bob);
+            ink_env::test::set_value_transferred::<Environment>(3 * BET_PRICE);
+            assert_eq!(contract.deposit(), Ok(3 * BET_PRICE));
+
+            ink_env::test::set_value_transferred::<Environment>(0);
+            assert_eq!(contract.register_ticket(get_win_ticket(), 2), Ok(()));
+            assert_eq!(contract.register_box_ticket(vec![1, 2, 3]), Ok(()));
+            assert_eq!(BET_PRICE / 2, contract.get_balance(default_accounts.bob));
+            assert_eq!(
+                contract.register_ticket(get_win_ticket(), 1),
+                Err(Error::TicketCosts)
+            );
+
+            let bob_balance =
+                ink_env::test::get_account_balance::<Environment>(default_accounts.bob).unwrap();
+            assert_eq!(
+                contract.withdraw_balance(BET_PRICE),
+                Err(Error::InsufficientBalance)
+            );
+            assert_eq!(contract.withdraw_balance(BET_PRICE / 2), Ok(()));
+            assert_eq!(
+                bob_balance + BET_PRICE / 2,
+                ink_env::test::get_account_balance::<Environment>(default_accounts.bob).unwrap()
+            );
+            assert_eq!(0, contract.check_solvency().ledger.balances);
+        }
+
+        #[ink::test]
+        fn prizes_can_be_credited_to_balance() {
+            use_random_chain_extension();
+            fund_contract();
+            let default_accounts = default_accounts();
+            let mut contract = Lottery::new();
+            set_next_caller(default_accounts.bob);
+            assert_eq!(contract.set_prizes_to_balance(true), Ok(()));
+            assert!(contract.get_prizes_to_balance(default_accounts.bob));
+            assert_eq!(contract.register_ticket(get_win_ticket(), 1), Ok(()));
+            set_next_caller(default_accounts.charlie);
+            assert_eq!(contract.register_ticket(vec![1, 1, 1], 1), Ok(()));
+            let bob_balance =
+                ink_env::test::get_account_balance::<Environment>(default_accounts.bob).unwrap();
+            contract.draw();
+
+            assert_eq!(2 * BET_PRICE, contract.get_balance(default_accounts.bob));
+            assert_eq!(
+                bob_balance,
+                ink_env::test::get_account_balance::<Environment>(default_accounts.bob).unwrap()
+            );
+            assert_eq!(0, contract.get_unclaimed_prizes());
+            assert!(contract.check_solvency().solvent);
+        }
+
+        #[ink::test]
+        fn register_shares_paying_one_fails() {
+            fund_contract();
+            let mut contract = Lottery::new();
+            set_next_caller(default_accounts().alice);
+            assert_eq!(
+                contract.register_ticket(get_win_ticket(), 2),
+                Err(Error::TicketCosts)
+            );
+        }
+
+        #[ink::test]
+        fn register_zero_shares_fails() {
+            let mut contract = Lottery::new();
+            set_next_caller(default_accounts().alice);
+            assert_eq!(
+                ink_env::pay_with_call!(contract.register_ticket(get_win_ticket(), 0), 0),
+                Err(Error::InvalidQuantity)
+            );
+            assert_eq!(contract.get_holder_count(get_win_ticket()), 0);
+        }
+
+        #[ink::test]
+        fn jackpot_is_split_by_shares() {
+            use_random_chain_extension();
+            fund_contract();
+            let default_accounts = default_accounts();
+            let mut contract = Lottery::new();
+            set_next_caller(default_accounts.charlie);
+            assert_eq!(
+                ink_env::pay_with_call!(
+                    contract.register_ticket(get_win_ticket(), 3),
+                    BET_PRICE * 3
+                ),
+                Ok(())
+            );
+            set_next_caller(default_accounts.bob);
+            assert_eq!(contract.register_ticket(get_win_ticket(), 1), Ok(()));
+            let charlie_balance =
+                ink_env::test::get_account_balance::<Environment>(default_accounts.charlie)
+                    .unwrap();
+            let bob_balance =
+                ink_env::test::get_account_balance::<Environment>(default_accounts.bob).unwrap();
+            contract.draw();
+
+            assert_eq!(
+                4,
+                contract.get_draw_result(0).unwrap().winning_tickets[0].winners
+            );
+            assert_eq!(1_000_000, contract.get_last_pot_per_ticket());
+            assert_eq!(
+                charlie_balance + 3_000_000,
+                ink_env::test::get_account_balance::<Environment>(default_accounts.charlie)
+                    .unwrap()
+            );
+            assert_eq!(
+                bob_balance + 1_000_000,
+                ink_env::test::get_account_balance::<Environment>(default_accounts.bob).unwrap()
+            );
+        }
+
+        #[ink::test]
+        fn tickets_of_account_are_listed_per_round() {
+            let default_accounts = default_accounts();
+            let mut contract = Lottery::new();
+            set_next_caller(default_accounts.alice);
+            assert_eq!(contract.register_ticket(vec![1, 2, 3], 1), Ok(()));
+            assert_eq!(contract.register_ticket(get_win_ticket(), 1), Ok(()));
+            assert_eq!(
+                ink_env::pay_with_call!(contract.register_ticket(vec![1, 2, 3], 2), BET_PRICE * 2),
+                Ok(())
+            );
+            set_next_caller(default_accounts.bob);
+            assert_eq!(contract.register_ticket(vec![1, 2, 3], 1), Ok(()));
+
+            assert_eq!(
+                contract.get_tickets_of(default_accounts.alice, 0, 0, 8),
+                vec![
+                    AccountTicket {
+                        kind: BetKind::Ticket,
+                        round: 0,
+                        ticket: vec![1, 2, 3],
+                        shares: 3,
+                    },
+                    AccountTicket {
+                        kind: BetKind::Ticket,
+                        round: 0,
+                        ticket: get_win_ticket(),
+                        shares: 1,
+                    },
+                ]
+            );
+            assert_eq!(
+                contract.get_tickets_of(default_accounts.alice, 0, 1, 8)[0].ticket,
+                get_win_ticket()
+            );
+            assert_eq!(
+                contract.get_tickets_of(default_accounts.bob, 0, 0, 8).len(),
+                1
+            );
+            assert!(contract
+                .get_tickets_of(default_accounts.alice, 1, 0, 8)
+                .is_empty());
+        }
+
+        #[ink::test]
+        fn ticket_history_spans_rounds() {
+            use_random_chain_extension();
+            fund_contract();
+            let default_accounts = default_accounts();
+            let mut contract = Lottery::new();
+            set_next_caller(default_accounts.bob);
+            assert_eq!(contract.register_ticket(get_win_ticket(), 1), Ok(()));
+            contract.draw();
+            assert_eq!(contract.register_ticket(vec![1, 2, 3], 1), Ok(()));
+
+            let history = contract.get_ticket_history(default_accounts.bob, 0, 8);
+            assert_eq!(
+                history
+                    .iter()
+                    .map(|entry| (entry.round, entry.ticket.clone()))
+                    .collect::<Vec<_>>(),
+                vec![(0, get_win_ticket()), (1, vec![1, 2, 3])]
+            );
+            assert_eq!(
+                contract.get_ticket_history(default_accounts.bob, 1, 1),
+                history[1..]
+            );
+            assert_eq!(
+                contract.get_tickets_of(default_accounts.bob, 1, 0, 8),
+                history[1..]
+            );
+        }
+
+        #[ink::test]
+        fn round_stats_count_sales_and_draws() {
+            use_random_chain_extension();
+            fund_contract();
+            let default_accounts = default_accounts();
+            let mut contract = Lottery::new();
+            set_next_caller(default_accounts.bob);
+            assert_eq!(
+                ink_env::pay_with_call!(contract.register_ticket(vec![1, 2, 3], 2), BET_PRICE * 2),
+                Ok(())
+            );
+            set_next_caller(default_accounts.bob);
+            assert_eq!(contract.register_ticket(get_win_ticket(), 1), Ok(()));
+            set_next_caller(default_accounts.charlie);
+            assert_eq!(contract.register_ticket(vec![1, 2, 3], 1), Ok(()));
+            contract.draw();
+
+            assert_eq!(
+                contract.get_round_stats(0),
+                RoundStats {
+                    tickets_sold: 3,
+                    shares_sold: 4,
+                    unique_players: 2,
+                    distinct_combinations: 2,
+                    shares_per_ticket: 2,
+                    total_collected: BET_PRICE * 4,
+                    draws: 1,
+                }
+            );
+            assert_eq!(contract.get_round_stats(1), RoundStats::default());
+        }
+
+        #[ink::test]
+        fn round_stats_include_box_bets() {
+            let default_accounts = default_accounts();
+            let mut contract = Lottery::with_config(box_config());
+            set_next_caller(default_accounts.alice);
+            assert_eq!(contract.register_ticket(vec![1, 2, 3], 1), Ok(()));
+            assert_eq!(
+                ink_env::pay_with_call!(contract.register_box_ticket(vec![3, 2, 1]), BET_PRICE / 2),
+                Ok(())
+            );
+
+            let stats = contract.get_round_stats(0);
+            assert_eq!(stats.tickets_sold, 2);
+            assert_eq!(stats.unique_players, 1);
+            assert_eq!(stats.distinct_combinations, 2);
+            assert_eq!(stats.total_collected, BET_PRICE + BET_PRICE / 2);
+        }
+
+        /// Storage bytes of the holder records of a ticket in the actual round.
+        fn holder_storage_bytes(contract: &Lottery, ticket: &Ticket) -> u32 {
+            let totals_key = (BetKind::Ticket, ticket, contract.state.round);
+            let mut bytes = contract.ticket_totals.size(totals_key).unwrap_or(0);
+            let holders = contract.holders(
+                BetKind::Ticket,
+                ticket,
+                contract.state.round,
+                0,
+                MAX_PAGE_SIZE,
+            );
+            for (position, holder) in holders.into_iter().enumerate() {
+                let position = (
+                    BetKind::Ticket,
+                    ticket,
+                    contract.state.round,
+                    position as u32,
+                );
+                let key = (BetKind::Ticket, ticket, contract.state.round, holder);
+                bytes += contract.ticket_holders.size(position).unwrap_or(0)
+                    + contract.holder_positions.size(key).unwrap_or(0)
+                    + contract.holder_entries.size(key).unwrap_or(0);
+            }
+            bytes
+        }
+
+        #[ink::test]
+        fn purchases_store_fewer_bytes_than_legacy_layout() {
+            let default_accounts = default_accounts();
+            let mut contract = Lottery::new();
+            contract
+                .legacy_ticket_and_address
+                .insert(([1, 2, 3], 0), &[default_accounts.alice; 8]);
+            let legacy_bytes = contract
+                .legacy_ticket_and_address
+                .size(([1, 2, 3], 0))
+                .unwrap();
+            assert_eq!(256, legacy_bytes);
+
+            set_next_caller(default_accounts.alice);
+            contract = register_number_of_same_tickets(1, [21, 236, 123], contract);
+            let first_purchase = holder_storage_bytes(&contract, &get_win_ticket());
+            assert_eq!(42, first_purchase);
+
+            contract = register_number_of_same_tickets(7, [21, 236, 123], contract);
+            assert_eq!(
+                first_purchase,
+                holder_storage_bytes(&contract, &get_win_ticket())
+            );
+
+            set_next_caller(default_accounts.bob);
+            contract = register_number_of_same_tickets(1, [21, 236, 123], contract);
+            let second_holder = holder_storage_bytes(&contract, &get_win_ticket()) - first_purchase;
+            assert_eq!(40, second_holder);
+            assert!(second_holder * 6 < legacy_bytes);
+        }
+
+        #[ink::test]
+        fn legacy_holders_are_migrated() {
+            use_random_chain_extension();
+            let default_accounts = default_accounts();
+            let mut contract = Lottery::new();
+            let mut legacy_holders = [AccountId::default(); 8];
+            legacy_holders[0] = default_accounts.alice;
+            legacy_holders[1] = default_accounts.bob;
+            legacy_holders[2] = default_accounts.alice;
+            contract
+                .legacy_ticket_and_address
+                .insert(([21, 236, 123], 0), &legacy_holders);
+
+            assert_eq!(contract.migrate_legacy_holders([21, 236, 123], 0), Ok(3));
+            assert_eq!(contract.migrate_legacy_holders([21, 236, 123], 0), Ok(0));
+            assert!(!contract
+                .legacy_ticket_and_address
+                .contains(([21, 236, 123], 0)));
+            assert_eq!(
+                contract.get_accounts_by_ticket(get_win_ticket(), 0, 8),
+                vec![default_accounts.alice, default_accounts.bob]
+            );
+            assert_eq!(contract.get_share_count(get_win_ticket()), 3);
+        }
+
+        /// Storage of the first release, the fields in the order it declared them.
+        #[derive(SpreadLayout, SpreadAllocate)]
+        struct FirstReleaseLottery {
+            ticket_and_address: Mapping<([u8; 3], u8), [AccountId; 8]>,
+            round: u8,
+            last_drawing: BlockNumber,
+            jackpot: Balance,
+            winner_ticket: [u8; 3],
+            last_jackpot: Balance,
+            last_pot_per_ticket: Balance,
+            default_address: [AccountId; 8],
+        }
+
+        #[ink::test]
+        fn first_release_storage_loads_and_migrates() {
+            use_random_chain_extension();
+            fund_contract();
+            let default_accounts = default_accounts();
+            advance_blocks(10);
+            let root = Key::from([0x00; 32]);
+            let mut first_release = FirstReleaseLottery::allocate_spread(&mut KeyPtr::from(root));
+            first_release.round = 4;
+            first_release.last_drawing = 7;
+            first_release.jackpot = 3 * BET_PRICE;
+            first_release.winner_ticket = [1, 2, 3];
+            first_release.last_jackpot = BET_PRICE;
+            let mut holders = [AccountId::default(); 8];
+            holders[0] = default_accounts.bob;
+            first_release
+                .ticket_and_address
+                .insert(([21, 236, 123], 4), &holders);
+            push_spread_root(&first_release, &root);
+
+            let mut contract: Lottery = pull_spread_root(&root);
+            assert_eq!(3 * BET_PRICE, contract.get_jackpot());
+            assert_eq!(7, contract.get_last_drawing());
+            assert_eq!(Ticket::new(), contract.get_winner_ticket());
+            assert_eq!(
+                contract.migrate_legacy_holders([21, 236, 123], 4),
+                Err(Error::MigrationPending)
+            );
+            set_next_caller(default_accounts.eve);
+            assert_eq!(
+                contract.register_ticket(vec![1, 2, 3], 1),
+                Err(Error::MigrationPending)
+            );
+            assert_eq!(contract.deposit(), Err(Error::MigrationPending));
+            assert_eq!(contract.pay_out_winners(8), 0);
+            assert_eq!(contract.migrate_legacy_state(), Err(Error::NotOwner));
+            let legacy_owner = AccountId::from(LEGACY_OWNER);
+            set_next_caller(legacy_owner);
+            assert_eq!(contract.migrate_legacy_state(), Ok(()));
+            assert_eq!(contract.migrate_legacy_state(), Err(Error::AlreadyMigrated));
+            assert_eq!(contract.migrate_legacy_holders([21, 236, 123], 4), Ok(1));
+            push_spread_root(&contract, &root);
+
+            let contract: Lottery = pull_spread_root(&root);
+            assert_eq!(4, contract.get_state().round);
+            assert_eq!(vec![1, 2, 3], contract.get_winner_ticket());
+            assert_eq!(legacy_owner, contract.get_owner());
+            assert_eq!(
+                vec![default_accounts.bob],
+                contract.get_accounts_by_ticket(get_win_ticket(), 0, 8)
+            );
+            assert_eq!(BET_PRICE, contract.get_last_jackpot());
+        }
+
+        fn fee_config() -> LotteryConfig {
+            LotteryConfig {
+                house_fee: 1_000,
+                ..Default::default()
+            }
+        }
+
+        #[ink::test]
+        fn house_fee_goes_to_treasury() {
+            let mut contract = Lottery::with_config(fee_config());
+            set_next_caller(default_accounts().bob);
+            assert_eq!(contract.register_ticket(get_win_ticket(), 1), Ok(()));
+            assert_eq!(900_000, contract.get_jackpot());
+            assert_eq!(100_000, contract.get_treasury());
+            assert_eq!(BET_PRICE, contract.get_round_stats(0).total_collected);
+        }
+
+        #[ink::test]
+        fn only_owner_withdraws_treasury() {
+            let default_accounts = default_accounts();
+            fund_contract();
+            set_next_caller(default_accounts.bob);
+            let mut contract = Lottery::with_config(fee_config());
+            assert_eq!(contract.get_owner(), default_accounts.bob);
+            assert_eq!(contract.register_ticket(get_win_ticket(), 1), Ok(()));
+
+            set_next_caller(default_accounts.charlie);
+            assert_eq!(
+                contract.withdraw_treasury(default_accounts.charlie, 1),
+                Err(Error::NotOwner)
+            );
+            set_next_caller(default_accounts.bob);
+            assert_eq!(
+                contract.withdraw_treasury(default_accounts.eve, 100_001),
+                Err(Error::InsufficientTreasury)
+            );
+            let eve_balance =
+                ink_env::test::get_account_balance::<Environment>(default_accounts.eve).unwrap();
+            assert_eq!(
+                contract.withdraw_treasury(default_accounts.eve, 60_000),
+                Ok(())
+            );
+            assert_eq!(
+                eve_balance + 60_000,
+                ink_env::test::get_account_balance::<Environment>(default_accounts.eve).unwrap()
+            );
+            assert_eq!(40_000, contract.get_treasury());
+            assert_eq!(900_000, contract.get_jackpot());
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "invalid house fee!")]
+        fn house_fee_over_everything_should_panic() {
+            Lottery::with_config(LotteryConfig {
+                house_fee: 10_001,
+                ..Default::default()
+            });
+        }
+
+        #[ink::test]
+        fn solvency_compares_ledgers_with_balance() {
+            let contract_id = ink_env::test::callee::<Environment>();
+            let mut contract = Lottery::with_config(fee_config());
+            set_next_caller(default_accounts().bob);
+            assert_eq!(contract.register_ticket(get_win_ticket(), 1), Ok(()));
+
+            ink_env::test::set_account_balance::<Environment>(contract_id, BET_PRICE + 5);
+            assert_eq!(
+                contract.check_solvency(),
+                Solvency {
+                    ledger: Ledger {
+                        jackpot: 900_000,
+                        treasury: 100_000,
+                        claimable: 0,
+                        reserve: 0,
+                        seeded: 0,
+                        secondary_pot: 0,
+                        second_chance_pot: 0,
+                        bankroll: 0,
+                        principal: 0,
+                        subscriptions: 0,
+                        balances: 0,
+                        unallocated: 5,
+                    },
+                    liabilities: BET_PRICE,
+                    balance: BET_PRICE + 5,
+                    solvent: true,
+                }
+            );
+
+            ink_env::test::set_account_balance::<Environment>(contract_id, BET_PRICE - 1);
+            let solvency = contract.check_solvency();
+            assert!(!solvency.solvent);
+            assert_eq!(0, solvency.ledger.unallocated);
+        }
+
+        #[ink::test]
+        fn owner_sweeps_unallocated_into_treasury() {
+            let default_accounts = default_accounts();
+            let contract_id = ink_env::test::callee::<Environment>();
+            set_next_caller(default_accounts.bob);
+            let mut contract = Lottery::new();
+            assert_eq!(contract.register_ticket(get_win_ticket(), 1), Ok(()));
+            ink_env::test::set_account_balance::<Environment>(contract_id, BET_PRICE + 7);
+
+            set_next_caller(default_accounts.charlie);
+            assert_eq!(contract.sweep_unallocated(), Err(Error::NotOwner));
+            set_next_caller(default_accounts.bob);
+            assert_eq!(contract.sweep_unallocated(), Ok(7));
+            assert_eq!(7, contract.get_treasury());
+            assert_eq!(BET_PRICE, contract.get_jackpot());
+            assert_eq!(contract.sweep_unallocated(), Ok(0));
+        }
+
+        #[ink::test]
+        fn failed_payout_is_credited() {
+            use_random_chain_extension();
+            let default_accounts = default_accounts();
+            // a contract account without balance fails every transfer
+            let contract_id = AccountId::from([0x42; 32]);
+            ink_env::test::set_callee::<Environment>(contract_id);
+            let mut contract = Lottery::new();
+            set_next_caller(default_accounts.bob);
+            assert_eq!(contract.register_ticket(get_win_ticket(), 1), Ok(()));
+            contract.draw();
+
+            assert_eq!(0, contract.get_pending_payouts());
+            assert_eq!(BET_PRICE, contract.get_unclaimed_prizes());
+            assert_eq!(BET_PRICE, contract.get_claimable(default_accounts.bob));
+            assert_eq!(contract.withdraw_credit(), Err(Error::TransferFailed));
+
+            ink_env::test::set_account_balance::<Environment>(contract_id, BET_PRICE);
+            assert_eq!(contract.withdraw_credit(), Ok(BET_PRICE));
+            assert_eq!(0, contract.get_unclaimed_prizes());
+            assert_eq!(contract.withdraw_credit(), Err(Error::NoCredit));
+        }
+
+        #[ink::test]
+        fn sponsor_seeds_actual_jackpot() {
+            let default_accounts = default_accounts();
+            let mut contract = Lottery::new();
+            set_next_caller(default_accounts.charlie);
+            assert_eq!(
+                ink_env::pay_with_call!(
+                    contract.seed_jackpot(None, b"good luck".to_vec()),
+                    5_000_000
+                ),
+                Ok(())
+            );
+            assert_eq!(
+                ink_env::pay_with_call!(contract.seed_jackpot(Some(0), Vec::new()), 1_000_000),
+                Ok(())
+            );
+            assert_eq!(6_000_000, contract.get_jackpot());
+            assert_eq!(
+                6_000_000,
+                contract.get_sponsor_total(default_accounts.charlie)
+            );
+            assert_eq!(ink_env::test::recorded_events().count(), 2);
+        }
+
+        #[ink::test]
+        fn seeded_jackpot_starts_its_round() {
+            use_random_chain_extension();
+            fund_contract();
+            let default_accounts = default_accounts();
+            let mut contract = Lottery::new();
+            set_next_caller(default_accounts.charlie);
+            assert_eq!(
+                ink_env::pay_with_call!(contract.seed_jackpot(Some(1), Vec::new()), 3_000_000),
+                Ok(())
+            );
+            assert_eq!(0, contract.get_jackpot());
+            assert_eq!(3_000_000, contract.get_seeded_jackpot(1));
+            assert_eq!(3_000_000, contract.check_solvency().ledger.seeded);
+
+            set_next_caller(default_accounts.bob);
+            assert_eq!(contract.register_ticket(get_win_ticket(), 1), Ok(()));
+            contract.draw();
+            assert_eq!(BET_PRICE, contract.get_last_jackpot());
+            assert_eq!(3_000_000, contract.get_jackpot());
+            assert_eq!(0, contract.get_seeded_jackpot(1));
+            assert_eq!(0, contract.check_solvency().ledger.seeded);
+
+            assert_eq!(
+                ink_env::pay_with_call!(contract.seed_jackpot(Some(0), Vec::new()), 1),
+                Err(Error::InvalidRound)
+            );
+            assert_eq!(
+                ink_env::pay_with_call!(contract.seed_jackpot(None, Vec::new()), 0),
+                Err(Error::NoValue)
+            );
+        }
+
+        fn reserve_config() -> LotteryConfig {
+            LotteryConfig {
+                reserve_share: 5_000,
+                min_jackpot: 4_000_000,
+                ..Default::default()
+            }
+        }
+
+        #[ink::test]
+        fn reserve_share_goes_to_reserve() {
+            let mut contract = Lottery::with_config(reserve_config());
+            set_next_caller(default_accounts().bob);
+            assert_eq!(contract.register_ticket(get_win_ticket(), 1), Ok(()));
+            assert_eq!(500_000, contract.get_jackpot());
+            assert_eq!(500_000, contract.get_reserve());
+            assert_eq!(500_000, contract.check_solvency().ledger.reserve);
+        }
+
+        #[ink::test]
+        fn reserve_tops_up_small_jackpot() {
+            use_random_chain_extension();
+            fund_contract();
+            let default_accounts = default_accounts();
+            let mut contract = Lottery::with_config(reserve_config());
+            set_next_caller(default_accounts.charlie);
+            contract = register_number_of_same_tickets(4, [1, 1, 1], contract);
+            set_next_caller(default_accounts.bob);
+            assert_eq!(contract.register_ticket(get_win_ticket(), 1), Ok(()));
+            let events_before = ink_env::test::recorded_events().count();
+            contract.draw();
+
+            assert_eq!(4_000_000, contract.get_last_pot_per_ticket());
+            assert_eq!(1_000_000, contract.get_reserve());
+            let events: Vec<_> = ink_env::test::recorded_events()
+                .skip(events_before)
+                .collect();
+            assert_eq!(events.len(), 1);
+        }
+
+        #[ink::test]
+        fn reserve_is_kept_for_big_jackpot() {
+            use_random_chain_extension();
+            fund_contract();
+            let mut contract = Lottery::with_config(LotteryConfig {
+                min_jackpot: BET_PRICE / 4,
+                ..reserve_config()
+            });
+            set_next_caller(default_accounts().bob);
+            assert_eq!(contract.register_ticket(get_win_ticket(), 1), Ok(()));
+            contract.draw();
+
+            assert_eq!(500_000, contract.get_last_pot_per_ticket());
+            assert_eq!(500_000, contract.get_reserve());
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "invalid reserve share!")]
+        fn fee_and_reserve_over_everything_should_panic() {
+            Lottery::with_config(LotteryConfig {
+                house_fee: 6_000,
+                reserve_share: 5_000,
+                ..Default::default()
+            });
+        }
+
+        #[ink::test]
+        fn sales_beyond_cap_start_next_jackpot() {
+            use_random_chain_extension();
+            fund_contract();
+            let default_accounts = default_accounts();
+            let mut contract = Lottery::with_config(LotteryConfig {
+                jackpot_cap: Some(2_500_000),
+                ..Default::default()
+            });
+            set_next_caller(default_accounts.charlie);
+            contract = register_number_of_same_tickets(3, [1, 1, 1], contract);
+            assert_eq!(2_500_000, contract.get_jackpot());
+            assert_eq!(500_000, contract.get_secondary_pot());
+
+            set_next_caller(default_accounts.bob);
+            assert_eq!(contract.register_ticket(get_win_ticket(), 1), Ok(()));
+            contract.draw();
+            assert_eq!(2_500_000, contract.get_last_pot_per_ticket());
+            assert_eq!(1_500_000, contract.get_jackpot());
+            assert_eq!(0, contract.get_secondary_pot());
+        }
+
+        #[ink::test]
+        fn sales_beyond_cap_go_to_best_tier() {
+            use_random_chain_extension();
+            fund_contract();
+            let mut contract = Lottery::with_config(LotteryConfig {
+                jackpot_cap: Some(1_000_000),
+                overflow: Overflow::LowerTiers,
+                ..tier_config()
+            });
+            set_next_caller(default_accounts().charlie);
+            assert_eq!(contract.register_ticket(vec![21, 236, 0], 1), Ok(()));
+            assert_eq!(contract.register_ticket(vec![1, 1, 1], 1), Ok(()));
+            contract.draw();
+
+            let tiers = contract.get_draw_result(0).unwrap().tiers;
+            assert_eq!(tiers[0].prize_per_winner, 1_500_000);
+            assert_eq!(0, contract.get_secondary_pot());
+            assert_eq!(contract.claim_prize(0, vec![21, 236, 0]), Ok(1_500_000));
+        }
+
+        #[ink::test]
+        fn jackpot_must_be_won_by_closest_matches() {
+            use_random_chain_extension();
+            fund_contract();
+            let default_accounts = default_accounts();
+            let mut contract = Lottery::with_config(LotteryConfig {
+                must_be_won_after: Some(2),
+                ..Default::default()
+            });
+            set_next_caller(default_accounts.charlie);
+            assert_eq!(contract.register_ticket(vec![21, 236, 0], 1), Ok(()));
+            set_next_caller(default_accounts.bob);
+            assert_eq!(contract.register_ticket(vec![21, 0, 0], 1), Ok(()));
+            assert_eq!(contract.register_ticket(vec![1, 1, 1], 1), Ok(()));
+
+            contract.draw();
+            assert_eq!(None, contract.get_draw_result(0).unwrap().closest_matches);
+            assert_eq!(0, contract.get_state().round);
+            contract.draw();
+            assert_eq!(
+                contract.get_draw_result(1).unwrap().closest_matches,
+                Some(TierResult {
+                    matches: 2,
+                    bonus: false,
+                    winners: 1,
+                    prize_per_winner: 3_000_000,
+                })
+            );
+            assert_eq!(1, contract.get_state().round);
+            assert_eq!(0, contract.get_jackpot());
+            assert_eq!(contract.claim_prize(1, vec![21, 0, 0]), Err(Error::NoPrize));
+            set_next_caller(default_accounts.charlie);
+            assert_eq!(contract.claim_prize(1, vec![21, 236, 0]), Ok(3_000_000));
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "invalid jackpot cap!")]
+        fn zero_jackpot_cap_should_panic() {
+            Lottery::with_config(LotteryConfig {
+                jackpot_cap: Some(0),
+                ..Default::default()
+            });
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "invalid must be won rule!")]
+        fn must_be_won_without_drawing_should_panic() {
+            Lottery::with_config(LotteryConfig {
+                must_be_won_after: Some(0),
+                ..Default::default()
+            });
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "invalid must be won rule!")]
+        fn must_be_won_with_too_many_numbers_should_panic() {
+            Lottery::with_config(LotteryConfig {
+                ticket: TicketShape {
+                    numbers: MAX_MUST_BE_WON_NUMBERS + 1,
+                    ..Default::default()
+                },
+                must_be_won_after: Some(2),
+                ..Default::default()
+            });
+        }
+
+        #[ink::test]
+        fn runner_up_tickets_win_their_share() {
+            use_random_chain_extension();
+            fund_contract();
+            let default_accounts = default_accounts();
+            let config = LotteryConfig {
+                runner_ups: vec![2_000, 1_000],
+                ..Default::default()
+            };
+            let tickets = draw_winner_tickets(&config, get_win_ticket_chain_extension());
+            let mut contract = Lottery::with_config(config);
+            set_next_caller(default_accounts.charlie);
+            assert_eq!(contract.register_ticket(tickets[1].clone(), 1), Ok(()));
+            set_next_caller(default_accounts.bob);
+            contract = register_number_of_same_tickets(4, [1, 1, 1], contract);
+            let charlie_balance =
+                ink_env::test::get_account_balance::<Environment>(default_accounts.charlie)
+                    .unwrap();
+            contract.draw();
+
+            let result = contract.get_draw_result(0).unwrap();
+            assert_eq!(
+                result.winning_tickets[1],
+                WinningTicket {
+                    ticket: tickets[1].clone(),
+                    winners: 1,
+                    prize_per_winner: 1_000_000,
+                }
+            );
+            assert_eq!(result.winning_tickets[2].winners, 0);
+            assert_eq!(
+                charlie_balance + 1_000_000,
+                ink_env::test::get_account_balance::<Environment>(default_accounts.charlie)
+                    .unwrap()
+            );
+            assert_eq!(4_000_000, contract.get_jackpot());
+            assert_eq!(0, contract.get_state().round);
+        }
+
+        #[ink::test]
+        fn pending_winners_are_paid_for_their_shares_at_drawing() {
+            use_random_chain_extension();
+            fund_contract();
+            let config = LotteryConfig {
+                runner_ups: vec![5_000],
+                ..Default::default()
+            };
+            let tickets = draw_winner_tickets(&config, get_win_ticket_chain_extension());
+            let mut contract = Lottery::with_config(config);
+            for i in 0..9 {
+                set_next_caller(AccountId::from([i + 10; 32]));
+                assert_eq!(contract.register_ticket(tickets[1].clone(), 1), Ok(()));
+            }
+            set_next_caller(default_accounts().bob);
+            assert_eq!(contract.register_ticket(vec![1, 1, 1], 1), Ok(()));
+            contract.draw();
+            let prize = contract.get_draw_result(0).unwrap().winning_tickets[1].prize_per_winner;
+            assert_eq!(1, contract.get_pending_payouts());
+            assert_eq!(prize, contract.get_unclaimed_prizes());
+
+            let last = AccountId::from([18; 32]);
+            set_next_caller(last);
+            ink_env::test::set_value_transferred::<Environment>(5 * BET_PRICE);
+            assert_eq!(contract.register_ticket(tickets[1].clone(), 5), Ok(()));
+            let balance = ink_env::test::get_account_balance::<Environment>(last).unwrap();
+            assert_eq!(contract.pay_out_winners(10), 1);
+            assert_eq!(
+                balance + prize,
+                ink_env::test::get_account_balance::<Environment>(last).unwrap()
+            );
+            assert_eq!(0, contract.get_unclaimed_prizes());
+        }
 
-        fn default_accounts() -> ink_env::test::DefaultAccounts<CustomEnvironment> {
-            ink_env::test::default_accounts::<Environment>()
+        #[ink::test]
+        fn winner_tickets_are_distinct_and_stored() {
+            use_random_chain_extension();
+            let mut contract = Lottery::with_config(LotteryConfig {
+                ticket: TicketShape {
+                    numbers: 1,
+                    min: 0,
+                    max: 3,
+                    ordered: true,
+                    duplicates: false,
+                },
+                runner_ups: vec![1_000, 1_000, 1_000],
+                ..Default::default()
+            });
+            contract.draw();
+
+            let mut tickets = contract.get_winner_tickets();
+            assert_eq!(tickets[0], contract.get_winner_ticket());
+            assert_eq!(
+                tickets,
+                contract
+                    .get_draw_result(0)
+                    .unwrap()
+                    .winning_tickets
+                    .into_iter()
+                    .map(|winning| winning.ticket)
+                    .collect::<Vec<_>>()
+            );
+            tickets.sort_unstable();
+            assert_eq!(tickets, vec![vec![0], vec![1], vec![2], vec![3]]);
         }
 
-        fn set_next_caller(caller: AccountId) {
-            ink_env::test::set_caller::<Environment>(caller);
-            ink_env::test::set_account_balance::<Environment>(caller, BET_PRICE * 255);
-            ink_env::test::set_value_transferred::<Environment>(BET_PRICE);
+        #[ink::test]
+        #[should_panic(expected = "invalid runner-ups!")]
+        fn more_winner_tickets_than_combinations_should_panic() {
+            Lottery::with_config(LotteryConfig {
+                ticket: TicketShape {
+                    numbers: 2,
+                    min: 1,
+                    max: 3,
+                    ordered: false,
+                    duplicates: false,
+                },
+                runner_ups: vec![1_000; 3],
+                ..Default::default()
+            });
         }
 
-        fn set_next_caller_too_low_balance(caller: AccountId) {
-            let too_low_price = BET_PRICE - 1;
-            ink_env::test::set_caller::<Environment>(caller);
-            ink_env::test::set_value_transferred::<Environment>(too_low_price);
+        #[ink::test]
+        fn second_chance_pot_goes_to_a_player_without_winner() {
+            use_random_chain_extension();
+            fund_contract();
+            let default_accounts = default_accounts();
+            let mut contract = Lottery::with_config(LotteryConfig {
+                second_chance_share: 1_000,
+                ..Default::default()
+            });
+            set_next_caller(default_accounts.charlie);
+            assert_eq!(contract.register_ticket(vec![1, 1, 1], 1), Ok(()));
+            set_next_caller(default_accounts.bob);
+            contract = register_number_of_same_tickets(2, [2, 2, 2], contract);
+            assert_eq!(300_000, contract.get_second_chance_pot());
+            assert_eq!(2_700_000, contract.get_jackpot());
+            assert_eq!(
+                vec![default_accounts.charlie, default_accounts.bob],
+                contract.get_round_players(0, 0, 8)
+            );
+            let bob_balance =
+                ink_env::test::get_account_balance::<Environment>(default_accounts.bob).unwrap();
+            contract.draw();
+
+            let seed = tagged_seed(get_win_ticket_chain_extension(), SECOND_CHANCE_TAG);
+            assert_eq!(1, RandomNumbers::new(seed).next_below(2));
+            assert_eq!(
+                contract.get_draw_result(0).unwrap().second_chance_winner,
+                Some(RaffleWinner {
+                    position: 1,
+                    account: default_accounts.bob,
+                    prize: 300_000,
+                })
+            );
+            assert_eq!(
+                bob_balance + 300_000,
+                ink_env::test::get_account_balance::<Environment>(default_accounts.bob).unwrap()
+            );
+            assert_eq!(0, contract.get_second_chance_pot());
+            assert_eq!(2_700_000, contract.get_jackpot());
         }
 
-        fn set_next_caller_too_high_balance(caller: AccountId) {
-            let too_high_price = BET_PRICE + 1;
-            ink_env::test::set_caller::<Environment>(caller);
-            ink_env::test::set_value_transferred::<Environment>(too_high_price);
+        #[ink::test]
+        fn second_chance_pot_stays_when_jackpot_is_won() {
+            use_random_chain_extension();
+            fund_contract();
+            let mut contract = Lottery::with_config(LotteryConfig {
+                second_chance_share: 1_000,
+                ..Default::default()
+            });
+            set_next_caller(default_accounts().bob);
+            assert_eq!(contract.register_ticket(get_win_ticket(), 1), Ok(()));
+            contract.draw();
+
+            assert_eq!(
+                None,
+                contract.get_draw_result(0).unwrap().second_chance_winner
+            );
+            assert_eq!(100_000, contract.get_second_chance_pot());
+            assert!(contract.check_solvency().solvent);
         }
 
-        fn register_number_of_same_tickets(
-            num_registers: u8,
-            ticket: [u8; 3],
-            mut contract: Lottery,
-        ) -> Lottery {
-            for _i in 0..num_registers {
-                assert_eq!(contract.register_ticket(ticket), Ok(()));
+        #[ink::test]
+        #[should_panic(expected = "invalid second chance share!")]
+        fn second_chance_share_beyond_sales_should_panic() {
+            Lottery::with_config(LotteryConfig {
+                house_fee: 5_000,
+                second_chance_share: 5_001,
+                ..Default::default()
+            });
+        }
+
+        fn fixed_odds_config() -> LotteryConfig {
+            LotteryConfig {
+                mode: GameMode::FixedOdds,
+                fixed_odds: Some(FixedOdds {
+                    multiplier: 10,
+                    max_ticket_exposure: 15_000_000,
+                    max_round_exposure: 30_000_000,
+                }),
+                ..Default::default()
             }
+        }
+
+        fn fixed_odds_lottery() -> Lottery {
+            set_next_caller(default_accounts().bob);
+            let mut contract = Lottery::with_config(fixed_odds_config());
+            ink_env::test::set_value_transferred::<Environment>(10_000_000);
+            assert_eq!(contract.fund_bankroll(), Ok(()));
             contract
         }
 
-        fn get_win_ticket() -> [u8; 3] {
-            let mut ticket_arr = [0; 3];
-            ticket_arr[0] = 21;
-            ticket_arr[1] = 236;
-            ticket_arr[2] = 123;
+        #[ink::test]
+        fn fixed_odds_pay_a_multiple_of_the_stake() {
+            use_random_chain_extension();
+            fund_contract();
+            let mut contract = fixed_odds_lottery();
+            set_next_caller(default_accounts().charlie);
+            assert_eq!(contract.register_ticket(get_win_ticket(), 1), Ok(()));
+            assert_eq!(11_000_000, contract.get_bankroll());
+            assert_eq!(10_000_000, contract.get_round_exposure());
+            let charlie_balance =
+                ink_env::test::get_account_balance::<Environment>(default_accounts().charlie)
+                    .unwrap();
+            contract.draw();
 
-            return ticket_arr;
+            assert_eq!(
+                contract.get_draw_result(0).unwrap().winning_tickets[0].prize_per_winner,
+                10_000_000
+            );
+            assert_eq!(
+                charlie_balance + 10_000_000,
+                ink_env::test::get_account_balance::<Environment>(default_accounts().charlie)
+                    .unwrap()
+            );
+            assert_eq!(1_000_000, contract.get_bankroll());
+            assert_eq!(0, contract.get_round_exposure());
+            assert_eq!(0, contract.get_jackpot());
+            assert_eq!(1, contract.get_state().round);
         }
 
-        fn get_win_ticket_chain_extension() -> [u8; 32] {
-            let mut ticket_arr = [0; 32];
-            ticket_arr[0] = 21;
-            ticket_arr[1] = 236;
-            ticket_arr[2] = 123;
+        #[ink::test]
+        fn fixed_odds_bets_beyond_exposure_are_rejected() {
+            fund_contract();
+            let mut contract = fixed_odds_lottery();
+            set_next_caller(default_accounts().charlie);
+            assert_eq!(contract.register_ticket(vec![1, 1, 1], 1), Ok(()));
+            set_next_caller(default_accounts().charlie);
+            assert_eq!(
+                contract.register_ticket(vec![1, 1, 1], 1),
+                Err(Error::ExposureLimit)
+            );
+            set_next_caller(default_accounts().charlie);
+            assert_eq!(
+                contract.register_ticket(vec![2, 2, 2], 1),
+                Err(Error::ExposureLimit)
+            );
+            assert_eq!(10_000_000, contract.get_round_exposure());
+            assert_eq!(11_000_000, contract.get_bankroll());
+            assert!(contract.check_solvency().solvent);
+        }
 
-            return ticket_arr;
+        #[ink::test]
+        fn only_owner_funds_bankroll() {
+            let mut contract = fixed_odds_lottery();
+            set_next_caller(default_accounts().charlie);
+            fund_contract();
+            assert_eq!(contract.fund_bankroll(), Err(Error::NotOwner));
+            assert_eq!(
+                contract.seed_jackpot(None, Vec::new()),
+                Err(Error::WrongMode)
+            );
+            assert_eq!(10_000_000, contract.get_bankroll());
         }
 
-        fn setup_jackpot(numb_tickets: u8) -> Lottery {
-            use_random_chain_extension();
-            let mut contract = Lottery::new();
-            let default_accounts = default_accounts();
-            let mut ticket_arr = [0; 3];
-            set_next_caller(default_accounts.bob);
-            for i in 0..numb_tickets {
-                ticket_arr[0] = i;
-                ticket_arr[1] = i;
-                ticket_arr[2] = i;
-                assert_eq!(
-                    ink_env::pay_with_call!(contract.register_ticket(ticket_arr), BET_PRICE),
-                    Ok(())
-                );
-            }
-            return contract;
+        #[ink::test]
+        #[should_panic(expected = "invalid fixed odds!")]
+        fn fixed_odds_without_settings_should_panic() {
+            Lottery::with_config(LotteryConfig {
+                mode: GameMode::FixedOdds,
+                ..Default::default()
+            });
         }
 
-        fn register_number_of_win_tickets(num_registers: u8, mut contract: Lottery) -> Lottery {
-            let default_accounts = default_accounts();
+        #[ink::test]
+        fn liquidity_providers_share_the_bankroll() {
+            use_random_chain_extension();
+            fund_contract();
+            let accounts = default_accounts();
+            let mut contract = fixed_odds_lottery();
+            set_next_caller(accounts.eve);
+            ink_env::test::set_value_transferred::<Environment>(5_000_000);
+            assert_eq!(contract.deposit_liquidity(), Ok(5_000_000));
+            assert_eq!(10_000_000, contract.get_lp_shares(accounts.bob));
+            assert_eq!(5_000_000, contract.get_lp_shares(accounts.eve));
 
-            for i in 0..num_registers {
-                let acc = i % 3;
-                if acc == 0 {
-                    set_next_caller(default_accounts.eve);
-                } else if acc == 1 {
-                    set_next_caller(default_accounts.alice);
-                } else {
-                    set_next_caller(default_accounts.bob);
+            set_next_caller(accounts.charlie);
+            assert_eq!(contract.register_ticket(vec![1, 1, 1], 1), Ok(()));
+            contract.draw();
+            assert_eq!(
+                contract.get_pool(),
+                Pool {
+                    bankroll: 16_000_000,
+                    total_shares: 15_000_000,
+                    share_price: 1_066_666,
+                    exposure: 0,
+                    free: 16_000_000,
                 }
-                assert_eq!(
-                    ink_env::pay_with_call!(contract.register_ticket(get_win_ticket()), BET_PRICE),
-                    Ok(())
-                );
-            }
-            contract
-        }
+            );
 
-        fn use_random_chain_extension() {
-            struct MockedExtension;
-            impl ink_env::test::ChainExtension for MockedExtension {
-                fn func_id(&self) -> u32 {
-                    1101
-                }
-                fn call(&mut self, _input: &[u8], output: &mut Vec<u8>) -> u32 {
-                    let ret = get_win_ticket_chain_extension();
-                    scale::Encode::encode_to(&ret, output);
-                    0
-                }
-            }
-            ink_env::test::register_chain_extension(MockedExtension);
+            set_next_caller(accounts.eve);
+            ink_env::test::set_value_transferred::<Environment>(0);
+            assert_eq!(contract.withdraw_liquidity(5_000_000), Ok(5_333_333));
+            assert_eq!(0, contract.get_lp_shares(accounts.eve));
+            assert_eq!(10_666_667, contract.get_bankroll());
+            assert!(contract.check_solvency().solvent);
         }
 
-        fn advance_blocks(numb_blocks: u32) {
-            for _i in 0..numb_blocks {
-                ink_env::test::advance_block::<Environment>();
+        #[ink::test]
+        fn fixed_odds_rounds_go_past_256() {
+            use_random_chain_extension();
+            fund_contract();
+            let mut contract = fixed_odds_lottery();
+            for _ in 0..300 {
+                contract.draw();
             }
+            assert_eq!(300, contract.get_state().round);
+            set_next_caller(default_accounts().charlie);
+            assert_eq!(contract.register_ticket(vec![1, 1, 1], 1), Ok(()));
+            assert_eq!(1, contract.get_round_stats(300).tickets_sold);
+            assert_eq!(0, contract.get_round_stats(44).tickets_sold);
         }
 
         #[ink::test]
-        fn default_works() {
+        fn liquidity_deposit_minting_no_shares_fails() {
             use_random_chain_extension();
-            let mut contract = Lottery::new();
-            let init = contract.get_winner_ticket();
+            fund_contract();
+            let accounts = default_accounts();
+            let mut contract = fixed_odds_lottery();
+            set_next_caller(accounts.charlie);
+            assert_eq!(contract.register_ticket(vec![1, 1, 1], 1), Ok(()));
             contract.draw();
-            let second = contract.get_winner_ticket();
-            assert_ne!(init, second);
+            assert_eq!(11_000_000, contract.get_bankroll());
+
+            set_next_caller(accounts.eve);
+            ink_env::test::set_value_transferred::<Environment>(1);
+            assert_eq!(contract.deposit_liquidity(), Err(Error::InsufficientShares));
+            assert_eq!(0, contract.get_lp_shares(accounts.eve));
+            assert_eq!(11_000_000, contract.get_bankroll());
         }
 
-        /// We test if the default constructor does its job.
         #[ink::test]
-        fn register_works() {
-            let default_accounts = default_accounts();
-            let mut ticket = [0; 3];
-            ticket[0] = 1;
-            ticket[1] = 2;
-            ticket[2] = 3;
-            set_next_caller(default_accounts.alice);
+        fn liquidity_is_locked_while_bets_are_open() {
+            fund_contract();
+            let accounts = default_accounts();
+            let mut contract = fixed_odds_lottery();
+            set_next_caller(accounts.charlie);
+            assert_eq!(contract.register_ticket(vec![1, 1, 1], 1), Ok(()));
+
+            set_next_caller(accounts.bob);
+            assert_eq!(
+                contract.withdraw_liquidity(1_000_000),
+                Err(Error::WithdrawalLocked)
+            );
+            advance_blocks(LP_COOLDOWN);
+            assert_eq!(
+                contract.withdraw_liquidity(10_000_000),
+                Err(Error::ExposureLimit)
+            );
+            assert_eq!(contract.withdraw_liquidity(500_000), Ok(550_000));
+            assert_eq!(
+                contract.withdraw_liquidity(10_000_000),
+                Err(Error::InsufficientShares)
+            );
+        }
+
+        #[ink::test]
+        fn subscription_enters_ticket_every_round() {
+            use_random_chain_extension();
+            fund_contract();
+            let accounts = default_accounts();
             let mut contract = Lottery::new();
+            set_next_caller(accounts.bob);
+            ink_env::test::set_value_transferred::<Environment>(3 * BET_PRICE);
+            assert_eq!(contract.subscribe(vec![1, 2, 3], 3), Ok(0));
+            assert_eq!(BET_PRICE, contract.get_jackpot());
+            set_next_caller(accounts.charlie);
+            assert_eq!(contract.register_ticket(get_win_ticket(), 1), Ok(()));
+            contract.draw();
 
-            assert_eq!(contract.register_ticket(ticket), Ok(()));
+            assert_eq!(1, contract.get_state().round);
+            assert_eq!(BET_PRICE, contract.get_jackpot());
+            assert_eq!(1, contract.get_share_count(vec![1, 2, 3]));
+            assert_eq!(
+                contract.get_subscriptions(accounts.bob),
+                vec![Subscription {
+                    id: 0,
+                    holder: accounts.bob,
+                    ticket: vec![1, 2, 3],
+                    rounds_left: 1,
+                }]
+            );
+            assert!(contract.check_solvency().solvent);
         }
 
         #[ink::test]
-        #[should_panic(expected = "insufficient funds!")]
-        fn transferred_balance_too_low() {
-            let default_accounts = default_accounts();
-            set_next_caller_too_low_balance(default_accounts.alice);
-            let ticket_arr = [0; 3];
+        fn subscriptions_are_capped_per_account_and_entered_in_batches() {
+            use_random_chain_extension();
+            fund_contract();
+            let accounts = default_accounts();
             let mut contract = Lottery::new();
+            set_next_caller(accounts.bob);
+            for id in 0..MAX_ACCOUNT_SUBSCRIPTIONS as u32 {
+                ink_env::test::set_value_transferred::<Environment>(2 * BET_PRICE);
+                assert_eq!(contract.subscribe(vec![1, 2, 3], 2), Ok(id));
+            }
+            ink_env::test::set_value_transferred::<Environment>(2 * BET_PRICE);
             assert_eq!(
-                contract.register_ticket(ticket_arr),
-                Err(Error::TicketCosts)
+                contract.subscribe(vec![1, 2, 3], 2),
+                Err(Error::TooManySubscriptions)
             );
+            ink_env::test::set_value_transferred::<Environment>(0);
+            assert_eq!(contract.cancel_subscription(0), Ok(BET_PRICE));
+            set_next_caller(accounts.charlie);
+            ink_env::test::set_value_transferred::<Environment>(2 * BET_PRICE);
+            assert_eq!(contract.subscribe(vec![4, 5, 6], 2), Ok(10));
+            assert_eq!(0, contract.get_pending_subscriptions());
+            assert_eq!(contract.register_ticket(get_win_ticket(), 1), Ok(()));
+            contract.draw();
+
+            let entered = SUBSCRIPTIONS_PER_DRAW;
+            assert_eq!(entered, contract.get_round_stats(1).tickets_sold);
+            // the cancelled subscription was dropped by the batch
+            assert_eq!(10 - entered, contract.get_pending_subscriptions());
+            assert_eq!(contract.enter_subscriptions(100), 10 - entered);
+            assert_eq!(0, contract.get_pending_subscriptions());
+            assert_eq!(10, contract.get_round_stats(1).tickets_sold);
+            assert_eq!(contract.enter_subscriptions(100), 0);
+            assert_eq!(0, contract.check_solvency().ledger.subscriptions);
+            assert!(contract.check_solvency().solvent);
         }
 
         #[ink::test]
-        #[should_panic(expected = "insufficient funds!")]
-        fn transferred_balance_too_high() {
-            let default_accounts = default_accounts();
-            set_next_caller_too_high_balance(default_accounts.alice);
-            let ticket_arr = [0; 3];
+        fn cancel_subscription_refunds_unused_rounds() {
+            fund_contract();
+            let accounts = default_accounts();
             let mut contract = Lottery::new();
+            set_next_caller(accounts.bob);
+            ink_env::test::set_value_transferred::<Environment>(3 * BET_PRICE);
+            assert_eq!(contract.subscribe(vec![1, 2, 3], 3), Ok(0));
+
+            set_next_caller(accounts.charlie);
             assert_eq!(
-                contract.register_ticket(ticket_arr),
-                Err(Error::TicketCosts)
+                contract.cancel_subscription(0),
+                Err(Error::SubscriptionNotFound)
+            );
+            set_next_caller(accounts.bob);
+            let bob_balance =
+                ink_env::test::get_account_balance::<Environment>(accounts.bob).unwrap();
+            assert_eq!(contract.cancel_subscription(0), Ok(2 * BET_PRICE));
+            assert_eq!(
+                bob_balance + 2 * BET_PRICE,
+                ink_env::test::get_account_balance::<Environment>(accounts.bob).unwrap()
+            );
+            assert_eq!(
+                Vec::<Subscription>::new(),
+                contract.get_subscriptions(accounts.bob)
+            );
+            assert_eq!(
+                contract.cancel_subscription(0),
+                Err(Error::SubscriptionNotFound)
             );
+            assert_eq!(0, contract.check_solvency().ledger.subscriptions);
         }
 
         #[ink::test]
-        fn get_accounts_by_lottery_default_should_be_default() {
-            let default_accounts = default_accounts();
-            set_next_caller(default_accounts.alice);
-            let contract = Lottery::default();
+        fn cancel_ticket_refunds_price_minus_fee() {
+            fund_contract();
+            let accounts = default_accounts();
+            let mut contract = Lottery::with_config(LotteryConfig {
+                cancellation_fee: 500,
+                ..tier_config()
+            });
+            set_next_caller(accounts.bob);
+            assert_eq!(contract.register_ticket(vec![1, 2, 3], 1), Ok(()));
+            assert_eq!(contract.register_ticket(vec![1, 2, 3], 1), Ok(()));
+            let bob_balance =
+                ink_env::test::get_account_balance::<Environment>(accounts.bob).unwrap();
+
+            assert_eq!(contract.cancel_ticket(vec![1, 2, 3], 0), Ok(1_900_000));
             assert_eq!(
-                contract.get_accounts_by_ticket([0; 3]),
-                [AccountId::default(); 8]
+                bob_balance + 1_900_000,
+                ink_env::test::get_account_balance::<Environment>(accounts.bob).unwrap()
+            );
+            assert_eq!(0, contract.get_share_count(vec![1, 2, 3]));
+            assert_eq!(0, contract.get_jackpot());
+            assert_eq!(100_000, contract.get_treasury());
+            assert_eq!(0, contract.get_round_stats(0).shares_sold);
+            assert_eq!(
+                contract.cancel_ticket(vec![1, 2, 3], 0),
+                Err(Error::NothingToCancel)
             );
         }
 
         #[ink::test]
-        fn get_accounts_by_ticket_init_should_be_default() {
-            let default_accounts = default_accounts();
-            set_next_caller(default_accounts.alice);
-            let contract = Lottery::new();
+        fn cancel_ticket_removes_holder_and_updates_stats() {
+            fund_contract();
+            let accounts = default_accounts();
+            let mut contract = Lottery::new();
+            set_next_caller(accounts.bob);
+            assert_eq!(contract.register_ticket(vec![1, 2, 3], 1), Ok(()));
+            assert_eq!(contract.register_ticket(vec![4, 5, 6], 1), Ok(()));
+            assert_eq!(contract.register_ticket(vec![4, 5, 6], 1), Ok(()));
+            set_next_caller(accounts.charlie);
+            assert_eq!(contract.register_ticket(vec![1, 2, 3], 1), Ok(()));
+
+            set_next_caller(accounts.bob);
+            assert_eq!(contract.cancel_ticket(vec![1, 2, 3], 0), Ok(BET_PRICE));
+            assert_eq!(
+                vec![accounts.charlie],
+                contract.get_accounts_by_ticket(vec![1, 2, 3], 0, 8)
+            );
+            assert_eq!(1, contract.get_holder_count(vec![1, 2, 3]));
+            assert_eq!(
+                contract.get_tickets_of(accounts.bob, 0, 0, 8),
+                vec![AccountTicket {
+                    kind: BetKind::Ticket,
+                    round: 0,
+                    ticket: vec![4, 5, 6],
+                    shares: 2,
+                }]
+            );
+            assert_eq!(1, contract.get_ticket_history(accounts.bob, 0, 8).len());
+            let stats = contract.get_round_stats(0);
+            assert_eq!(stats.tickets_sold, 3);
+            assert_eq!(stats.unique_players, 2);
+            assert_eq!(stats.distinct_combinations, 2);
+
+            assert_eq!(contract.cancel_ticket(vec![4, 5, 6], 0), Ok(2 * BET_PRICE));
+            assert!(contract.get_tickets_of(accounts.bob, 0, 0, 8).is_empty());
+            assert_eq!(vec![accounts.charlie], contract.get_round_players(0, 0, 8));
+            let stats = contract.get_round_stats(0);
+            assert_eq!(stats.tickets_sold, 1);
+            assert_eq!(stats.unique_players, 1);
+            assert_eq!(stats.distinct_combinations, 1);
+
+            assert_eq!(contract.register_ticket(vec![1, 2, 3], 1), Ok(()));
+            assert_eq!(
+                vec![accounts.charlie, accounts.bob],
+                contract.get_accounts_by_ticket(vec![1, 2, 3], 0, 8)
+            );
             assert_eq!(
-                contract.get_accounts_by_ticket([0; 3]),
-                [AccountId::default(); 8]
+                vec![accounts.charlie, accounts.bob],
+                contract.get_round_players(0, 0, 8)
             );
         }
 
         #[ink::test]
-        fn get_next_drawing_init_should_be_block_per_round() {
-            let contract = Lottery::new();
-            assert_eq!(BLOCKS_PER_ROUND, contract.get_next_drawing())
+        fn cancel_ticket_fee_covers_withdrawn_house_fee() {
+            fund_contract();
+            let accounts = default_accounts();
+            let mut contract = Lottery::with_config(LotteryConfig {
+                cancellation_fee: 2_000,
+                ..fee_config()
+            });
+            set_next_caller(accounts.bob);
+            assert_eq!(contract.register_ticket(vec![1, 2, 3], 1), Ok(()));
+            set_next_caller(accounts.alice);
+            assert_eq!(contract.withdraw_treasury(accounts.alice, 100_000), Ok(()));
+            assert_eq!(0, contract.get_treasury());
+
+            set_next_caller(accounts.bob);
+            assert_eq!(contract.cancel_ticket(vec![1, 2, 3], 0), Ok(800_000));
+            assert_eq!(100_000, contract.get_treasury());
+            assert_eq!(0, contract.get_jackpot());
         }
 
         #[ink::test]
-        fn next_drawing_changed_after_first_draw() {
-            let mut contract = setup_jackpot(8);
-            let default_accounts = default_accounts();
-            let ticket_arr = [0; 3];
-            let old_next_drawing = contract.get_next_drawing();
-            advance_blocks(BLOCKS_PER_ROUND);
-            set_next_caller(default_accounts.bob);
-            assert_eq!(contract.register_ticket(ticket_arr), Ok(()));
-            assert_ne!(old_next_drawing, contract.get_next_drawing());
+        fn cancel_ticket_keeps_withdrawn_house_fee() {
+            fund_contract();
+            let accounts = default_accounts();
+            let mut contract = Lottery::with_config(LotteryConfig {
+                cancellation_fee: 500,
+                ..fee_config()
+            });
+            set_next_caller(accounts.bob);
+            assert_eq!(contract.register_ticket(vec![1, 2, 3], 1), Ok(()));
+            set_next_caller(accounts.alice);
+            assert_eq!(contract.withdraw_treasury(accounts.alice, 100_000), Ok(()));
+
+            set_next_caller(accounts.bob);
+            assert_eq!(contract.cancel_ticket(vec![1, 2, 3], 0), Ok(900_000));
+            assert_eq!(0, contract.get_treasury());
+            assert_eq!(0, contract.get_jackpot());
+            assert!(contract.check_solvency().solvent);
         }
 
         #[ink::test]
-        fn last_winner_ticket_changed_after_first_draw() {
-            let mut contract = setup_jackpot(8);
-            let default_accounts = default_accounts();
-            let ticket_arr = [0; 3];
-            let old_win_ticket = contract.get_winner_ticket();
-            advance_blocks(BLOCKS_PER_ROUND);
-            set_next_caller(default_accounts.bob);
-            assert_eq!(contract.register_ticket(ticket_arr), Ok(()));
-            assert_ne!(get_win_ticket(), old_win_ticket)
+        fn cancel_ticket_closes_before_drawing() {
+            use_random_chain_extension();
+            fund_contract();
+            let mut contract = Lottery::new();
+            set_next_caller(default_accounts().bob);
+            assert_eq!(contract.register_ticket(vec![1, 2, 3], 1), Ok(()));
+            assert_eq!(
+                contract.cancel_ticket(vec![1, 2, 3], 1),
+                Err(Error::CancellationClosed)
+            );
+            advance_blocks(BLOCKS_PER_ROUND - CANCELLATION_CUTOFF);
+            assert_eq!(
+                contract.cancel_ticket(vec![1, 2, 3], 0),
+                Err(Error::CancellationClosed)
+            );
+            contract.draw();
+            assert_eq!(
+                contract.cancel_ticket(vec![1, 2, 3], 0),
+                Err(Error::NothingToCancel)
+            );
+        }
+
+        fn no_loss_lottery() -> Lottery {
+            Lottery::with_config(LotteryConfig {
+                mode: GameMode::NoLoss,
+                ..Default::default()
+            })
         }
 
         #[ink::test]
-        fn last_drawing_changed_after_first_draw() {
-            let mut contract = setup_jackpot(8);
-            let default_accounts = default_accounts();
-            let ticket_arr = [0; 3];
-            let old_last_drawing = contract.get_last_drawing();
-            advance_blocks(BLOCKS_PER_ROUND);
-            set_next_caller(default_accounts.bob);
-            assert_eq!(contract.register_ticket(ticket_arr), Ok(()));
-            assert_ne!(old_last_drawing, contract.get_last_drawing());
+        fn no_loss_weight_grows_with_principal_and_time() {
+            fund_contract();
+            let accounts = default_accounts();
+            let mut contract = no_loss_lottery();
+            set_next_caller(accounts.bob);
+            assert_eq!(contract.deposit_principal(), Ok(()));
+            advance_blocks(10);
+            assert_eq!(10 * BET_PRICE, contract.get_weight(accounts.bob));
+            set_next_caller(accounts.bob);
+            assert_eq!(contract.deposit_principal(), Ok(()));
+            advance_blocks(5);
+            assert_eq!(20 * BET_PRICE, contract.get_weight(accounts.bob));
+
+            set_next_caller(accounts.bob);
+            let bob_balance =
+                ink_env::test::get_account_balance::<Environment>(accounts.bob).unwrap();
+            assert_eq!(contract.withdraw_principal(BET_PRICE), Ok(()));
+            assert_eq!(BET_PRICE, contract.get_principal(accounts.bob));
+            assert_eq!(
+                bob_balance + BET_PRICE,
+                ink_env::test::get_account_balance::<Environment>(accounts.bob).unwrap()
+            );
+            assert_eq!(
+                contract.withdraw_principal(2 * BET_PRICE),
+                Err(Error::InsufficientPrincipal)
+            );
+            assert_eq!(
+                contract.withdraw_principal(BET_PRICE / 2),
+                Err(Error::InsufficientPrincipal)
+            );
+            set_next_caller(accounts.eve);
+            ink_env::test::set_value_transferred::<Environment>(MIN_PRINCIPAL - 1);
+            assert_eq!(
+                contract.deposit_principal(),
+                Err(Error::InsufficientPrincipal)
+            );
+            assert_eq!(0, contract.get_principal(accounts.eve));
+            set_next_caller(accounts.bob);
+            assert_eq!(
+                contract.register_ticket(get_win_ticket(), 1),
+                Err(Error::WrongMode)
+            );
         }
 
         #[ink::test]
-        fn get_accounts_by_ticket_should_be_alice() {
-            let default_accounts = default_accounts();
-            set_next_caller(default_accounts.alice);
-            let mut contract = Lottery::new();
+        fn no_loss_drawing_pays_the_sponsored_jackpot() {
+            use_random_chain_extension();
+            fund_contract();
+            let accounts = default_accounts();
+            let mut contract = no_loss_lottery();
+            set_next_caller(accounts.charlie);
+            assert_eq!(contract.deposit_principal(), Ok(()));
+            set_next_caller(accounts.bob);
+            ink_env::test::set_value_transferred::<Environment>(3 * BET_PRICE);
+            assert_eq!(contract.deposit_principal(), Ok(()));
+            set_next_caller(accounts.eve);
+            assert_eq!(contract.seed_jackpot(None, Vec::new()), Ok(()));
+            advance_blocks(10);
+            contract.draw();
 
-            let mut ticket = [0; 3];
-            ticket[0] = 1;
-            ticket[1] = 2;
-            ticket[2] = 3;
-            contract = register_number_of_same_tickets(1, ticket, contract);
-            let mut winner_acc = [AccountId::default(); 8];
-            winner_acc[0] = default_accounts.alice;
-            assert_eq!(contract.get_accounts_by_ticket(ticket), winner_acc);
+            let winner = contract.get_draw_result(0).unwrap().raffle_winner.unwrap();
+            assert_eq!(winner.prize, BET_PRICE);
+            assert_eq!(vec![winner.account], contract.get_last_winners(0, 8));
+            assert_eq!(4 * BET_PRICE, contract.get_state().total_principal);
+            assert_eq!(0, contract.get_jackpot());
+            assert_eq!(1, contract.get_state().round);
+            assert_eq!(0, contract.get_weight(accounts.bob));
+            assert!(contract.check_solvency().solvent);
         }
 
         #[ink::test]
-        fn get_accounts_by_ticket_should_be_two_alice() {
-            let default_accounts = default_accounts();
-            let mut ticket = [0; 3];
-            ticket[0] = 1;
-            ticket[1] = 2;
-            ticket[2] = 3;
-            let mut winner_acc = [AccountId::default(); 8];
-            winner_acc[0] = default_accounts.alice;
-            winner_acc[1] = default_accounts.alice;
+        fn no_loss_drawing_frees_slots_of_withdrawn_depositors() {
+            use_random_chain_extension();
+            fund_contract();
+            let depositor = |index: u32| {
+                let mut account = [0xff; 32];
+                account[..4].copy_from_slice(&index.to_le_bytes());
+                AccountId::from(account)
+            };
+            let mut contract = no_loss_lottery();
+            for index in 0..MAX_DEPOSITORS {
+                set_next_caller(depositor(index));
+                assert_eq!(contract.deposit_principal(), Ok(()));
+            }
+            set_next_caller(default_accounts().bob);
+            assert_eq!(contract.deposit_principal(), Err(Error::TooManyDepositors));
+            set_next_caller(depositor(0));
+            assert_eq!(contract.withdraw_principal(BET_PRICE), Ok(()));
+            advance_blocks(10);
+            contract.draw();
 
-            set_next_caller(default_accounts.alice);
-            let mut contract = Lottery::new();
-            contract = register_number_of_same_tickets(2, ticket, contract);
+            set_next_caller(default_accounts().bob);
+            assert_eq!(contract.deposit_principal(), Ok(()));
+            assert_eq!(BET_PRICE, contract.get_principal(default_accounts().bob));
+            assert_eq!(0, contract.get_principal(depositor(0)));
+            advance_blocks(10);
+            assert_eq!(
+                10 * BET_PRICE,
+                contract.get_weight(depositor(MAX_DEPOSITORS - 1))
+            );
+        }
 
-            assert_eq!(contract.get_accounts_by_ticket(ticket), winner_acc);
+        fn raffle_config() -> LotteryConfig {
+            LotteryConfig {
+                mode: GameMode::Raffle,
+                ..Default::default()
+            }
         }
 
         #[ink::test]
-        fn ticket_filled_dont_panic() {
+        fn raffle_picks_entry_weighted_by_shares() {
+            use_random_chain_extension();
+            fund_contract();
             let default_accounts = default_accounts();
-            let mut ticket_arr = [0; 3];
-            ticket_arr[0] = 100;
-            ticket_arr[1] = 100;
-            ticket_arr[2] = 100;
-
+            let mut contract = Lottery::with_config(raffle_config());
+            set_next_caller(default_accounts.charlie);
+            assert_eq!(contract.enter_raffle(1), Ok(()));
             set_next_caller(default_accounts.bob);
-            let mut contract = Lottery::new();
+            assert_eq!(
+                ink_env::pay_with_call!(contract.enter_raffle(2), BET_PRICE * 2),
+                Ok(())
+            );
+            set_next_caller(default_accounts.eve);
+            assert_eq!(contract.enter_raffle(1), Ok(()));
+            let bob_balance =
+                ink_env::test::get_account_balance::<Environment>(default_accounts.bob).unwrap();
+            contract.draw();
 
-            // 8 is fine
-            for _i in 0..8 {
-                assert_eq!(contract.register_ticket(ticket_arr), Ok(()));
-            }
+            // the mocked seed draws position 1, the first share of bob
+            assert_eq!(
+                contract.get_draw_result(0).unwrap().raffle_winner,
+                Some(RaffleWinner {
+                    position: 1,
+                    account: default_accounts.bob,
+                    prize: 4_000_000,
+                })
+            );
+            assert_eq!(
+                bob_balance + 4_000_000,
+                ink_env::test::get_account_balance::<Environment>(default_accounts.bob).unwrap()
+            );
+            assert_eq!(vec![default_accounts.bob], contract.get_last_winners(0, 8));
+            assert_eq!(1, contract.get_state().round);
+            assert_eq!(0, contract.get_jackpot());
         }
 
         #[ink::test]
-        #[should_panic(expected = "ticket sold out!")]
-        fn ticket_sold_out() {
-            let default_accounts = default_accounts();
-            let mut ticket_arr = [0; 3];
-            ticket_arr[0] = 99;
-            ticket_arr[1] = 99;
-            ticket_arr[2] = 99;
+        fn raffle_without_entries_keeps_round() {
+            use_random_chain_extension();
+            let mut contract = Lottery::with_config(raffle_config());
+            contract.draw();
+            assert_eq!(None, contract.get_draw_result(0).unwrap().raffle_winner);
+            assert_eq!(0, contract.get_state().round);
+        }
 
-            set_next_caller(default_accounts.bob);
-            let mut contract = Lottery::new();
+        #[ink::test]
+        fn bets_need_their_game_mode() {
+            fund_contract();
+            set_next_caller(default_accounts().bob);
+            let mut raffle = Lottery::with_config(raffle_config());
+            assert_eq!(
+                raffle.register_ticket(get_win_ticket(), 1),
+                Err(Error::WrongMode)
+            );
+            let mut numbers = Lottery::new();
+            assert_eq!(numbers.enter_raffle(1), Err(Error::WrongMode));
+        }
 
-            for _i in 0..9 {
-                assert_eq!(contract.register_ticket(ticket_arr), Ok(()));
-            }
+        #[ink::test]
+        #[should_panic(expected = "invalid raffle!")]
+        fn raffle_with_prize_tiers_should_panic() {
+            Lottery::with_config(LotteryConfig {
+                mode: GameMode::Raffle,
+                ..tier_config()
+            });
+        }
+
+        #[ink::test]
+        fn state_of_new_lottery() {
+            let contract = Lottery::new();
+            let state = contract.get_state();
+            assert_eq!(state.round, 0);
+            assert_eq!(state.phase, Phase::Open);
+            assert_eq!(state.config, LotteryConfig::default());
+            assert_eq!(state.jackpot, 0);
+            assert_eq!(state.next_drawing, BLOCKS_PER_ROUND);
+            assert_eq!(state.claimable, 0);
+            assert_eq!(state.last_result, None);
+        }
+
+        #[ink::test]
+        fn state_shows_due_drawing() {
+            let contract = Lottery::new();
+            advance_blocks(BLOCKS_PER_ROUND);
+            assert_eq!(contract.get_state().phase, Phase::DrawDue);
         }
 
         #[ink::test]
@@ -618,8 +5705,7 @@ mod lottery {
         #[ink::test]
         fn winner_is_default_on_init() {
             let contract = Lottery::new();
-            let winners: [AccountId; 8] = [AccountId::default(); 8];
-            assert_eq!(winners, contract.get_last_winner_or_default())
+            assert_eq!(Vec::<AccountId>::new(), contract.get_last_winners(0, 8))
         }
 
         #[ink::test]
@@ -629,13 +5715,12 @@ mod lottery {
             set_next_caller(default_accounts.alice);
             let mut contract = Lottery::new();
 
-            assert_eq!(contract.register_ticket(get_win_ticket()), Ok(()));
+            assert_eq!(contract.register_ticket(get_win_ticket(), 1), Ok(()));
             advance_blocks(10);
             contract.draw();
 
-            let mut winners: [AccountId; 8] = [AccountId::default(); 8];
-            winners[0] = default_accounts.alice;
-            assert_eq!(winners, contract.get_last_winner_or_default())
+            let winners = vec![default_accounts.alice];
+            assert_eq!(winners, contract.get_last_winners(0, 8))
         }
 
         #[ink::test]
@@ -645,7 +5730,7 @@ mod lottery {
             set_next_caller(default_accounts.bob);
             let mut contract = Lottery::new();
 
-            assert_eq!(contract.register_ticket(get_win_ticket()), Ok(()));
+            assert_eq!(contract.register_ticket(get_win_ticket(), 1), Ok(()));
 
             set_next_caller(default_accounts.alice);
             let mut ticket_arr2 = [0; 3];
@@ -653,17 +5738,15 @@ mod lottery {
             ticket_arr2[1] = 1;
             ticket_arr2[2] = 1;
 
-            assert_eq!(contract.register_ticket(ticket_arr2), Ok(()));
+            assert_eq!(contract.register_ticket(ticket_arr2.to_vec(), 1), Ok(()));
 
             contract.draw();
-            let winner = contract.get_last_winner_or_default();
+            let winner = contract.get_last_winners(0, 8);
 
-            let mut should_be_winner: [AccountId; 8] = [AccountId::default(); 8];
-            should_be_winner[0] = default_accounts.bob;
+            let should_be_winner = vec![default_accounts.bob];
             assert_eq!(should_be_winner, winner);
 
-            let mut not_the_winner: [AccountId; 8] = [AccountId::default(); 8];
-            not_the_winner[0] = default_accounts.alice;
+            let not_the_winner = vec![default_accounts.alice];
             assert_ne!(not_the_winner, winner);
         }
 
@@ -679,7 +5762,7 @@ mod lottery {
         #[ink::test]
         fn test_255_applicants() {
             let mut contract = setup_jackpot(255);
-            assert_eq!(contract.register_ticket(get_win_ticket()), Ok(()));
+            assert_eq!(contract.register_ticket(get_win_ticket(), 1), Ok(()));
         }
 
         #[ink::test]
@@ -688,17 +5771,15 @@ mod lottery {
             use_random_chain_extension();
             set_next_caller(default_accounts.bob);
             let mut contract = Lottery::new();
-            assert_eq!(contract.register_ticket(get_win_ticket()), Ok(()));
+            assert_eq!(contract.register_ticket(get_win_ticket(), 1), Ok(()));
 
             set_next_caller(default_accounts.alice);
-            assert_eq!(contract.register_ticket(get_win_ticket()), Ok(()));
+            assert_eq!(contract.register_ticket(get_win_ticket(), 1), Ok(()));
 
             contract.draw();
             assert_eq!(get_win_ticket(), contract.get_winner_ticket());
-            let winner = contract.get_last_winner_or_default();
-            let mut should_win: [AccountId; 8] = [AccountId::default(); 8];
-            should_win[0] = default_accounts.bob;
-            should_win[1] = default_accounts.alice;
+            let winner = contract.get_last_winners(0, 8);
+            let should_win = vec![default_accounts.bob, default_accounts.alice];
             assert_eq!(should_win, winner);
         }
 
@@ -714,8 +5795,6 @@ mod lottery {
             let mut contract = setup_jackpot(5);
             contract = register_number_of_win_tickets(2, contract);
             contract.draw();
-            let winner = contract.get_winner_or_default();
-            contract.transfer_to_winners(winner);
 
             assert_eq!(3_500_000, contract.get_last_pot_per_ticket());
         }
@@ -726,10 +5805,8 @@ mod lottery {
             contract = register_number_of_win_tickets(3, contract);
 
             contract.draw();
-            let winner = contract.get_winner_or_default();
-            contract.transfer_to_winners(winner);
 
-            assert_eq!(2_000_000, contract.get_last_pot_per_ticket());
+            assert_eq!(2_666_666, contract.get_last_pot_per_ticket());
         }
 
         #[ink::test]
@@ -738,8 +5815,6 @@ mod lottery {
             contract = register_number_of_win_tickets(4, contract);
 
             contract.draw();
-            let winner = contract.get_winner_or_default();
-            contract.transfer_to_winners(winner);
 
             assert_eq!(2_250_000, contract.get_last_pot_per_ticket());
         }
@@ -750,8 +5825,6 @@ mod lottery {
             contract = register_number_of_win_tickets(8, contract);
 
             contract.draw();
-            let winner = contract.get_winner_or_default();
-            contract.transfer_to_winners(winner);
 
             assert_eq!(1_625_000, contract.get_last_pot_per_ticket());
         }
@@ -790,12 +5863,483 @@ mod lottery {
             ticket_arr2[1] = 1;
             ticket_arr2[2] = 1;
 
-            assert_eq!(contract.register_ticket(ticket), Ok(()));
-            assert_eq!(contract.register_ticket(ticket_arr2), Ok(()));
-            let account_ticket = contract.get_accounts_by_ticket(ticket);
+            assert_eq!(contract.register_ticket(ticket.to_vec(), 1), Ok(()));
+            assert_eq!(contract.register_ticket(ticket_arr2.to_vec(), 1), Ok(()));
+            let account_ticket = contract.get_accounts_by_ticket(ticket.to_vec(), 0, 8);
             contract.reset_game();
 
-            assert_ne!(contract.get_accounts_by_ticket(ticket), account_ticket);
+            assert_ne!(
+                contract.get_accounts_by_ticket(ticket.to_vec(), 0, 8),
+                account_ticket
+            );
+        }
+
+        fn tier_config() -> LotteryConfig {
+            LotteryConfig {
+                prize_tiers: vec![
+                    PrizeTier {
+                        matches: 2,
+                        bonus: false,
+                        prize: Prize::Share(5_000),
+                    },
+                    PrizeTier {
+                        matches: 1,
+                        bonus: false,
+                        prize: Prize::Fixed(100_000),
+                    },
+                ],
+                ..Default::default()
+            }
+        }
+
+        fn fund_contract() {
+            let contract_id = ink_env::test::callee::<Environment>();
+            ink_env::test::set_account_balance::<Environment>(contract_id, BET_PRICE * 255);
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "invalid prize tier!")]
+        fn prize_tier_for_all_numbers_should_panic() {
+            Lottery::with_config(LotteryConfig {
+                prize_tiers: vec![PrizeTier {
+                    matches: 3,
+                    bonus: false,
+                    prize: Prize::Share(1_000),
+                }],
+                ..Default::default()
+            });
+        }
+
+        #[ink::test]
+        fn partial_matches_are_reported_per_tier() {
+            let default_accounts = default_accounts();
+            use_random_chain_extension();
+            let mut contract = Lottery::with_config(tier_config());
+
+            set_next_caller(default_accounts.alice);
+            assert_eq!(contract.register_ticket(vec![21, 236, 0], 1), Ok(()));
+            set_next_caller(default_accounts.bob);
+            assert_eq!(contract.register_ticket(vec![0, 236, 0], 1), Ok(()));
+            assert_eq!(contract.register_ticket(vec![123, 21, 236], 1), Ok(()));
+            contract.draw();
+
+            let result = contract.get_draw_result(0).unwrap();
+            assert_eq!(result.winning_tickets[0].ticket, get_win_ticket());
+            assert_eq!(result.winning_tickets[0].winners, 0);
+            assert_eq!(
+                result.tiers,
+                vec![
+                    TierResult {
+                        matches: 2,
+                        bonus: false,
+                        winners: 1,
+                        prize_per_winner: 1_500_000,
+                    },
+                    TierResult {
+                        matches: 1,
+                        bonus: false,
+                        winners: 1,
+                        prize_per_winner: 100_000,
+                    },
+                ]
+            );
+            assert_eq!(1_400_000, contract.get_jackpot());
+            assert_eq!(1_600_000, contract.get_unclaimed_prizes());
+        }
+
+        #[ink::test]
+        fn claim_tier_prize_works_once() {
+            let default_accounts = default_accounts();
+            use_random_chain_extension();
+            fund_contract();
+            let mut contract = Lottery::with_config(tier_config());
+
+            set_next_caller(default_accounts.alice);
+            assert_eq!(contract.register_ticket(vec![21, 236, 0], 1), Ok(()));
+            assert_eq!(
+                ink_env::pay_with_call!(
+                    contract.register_ticket(vec![21, 236, 0], 2),
+                    BET_PRICE * 2
+                ),
+                Ok(())
+            );
+            contract.draw();
+
+            assert_eq!(3, contract.get_draw_result(0).unwrap().tiers[0].winners);
+            assert_eq!(contract.claim_prize(0, vec![21, 236, 0]), Ok(1_500_000));
+            assert_eq!(
+                contract.claim_prize(0, vec![21, 236, 0]),
+                Err(Error::PrizeAlreadyClaimed)
+            );
+            assert_eq!(0, contract.get_unclaimed_prizes());
+
+            set_next_caller(default_accounts.bob);
+            assert_eq!(
+                contract.claim_prize(0, vec![21, 236, 0]),
+                Err(Error::NoPrize)
+            );
+            assert_eq!(contract.claim_prize(0, vec![1, 1, 1]), Err(Error::NoPrize));
+            assert_eq!(
+                contract.claim_prize(1, vec![21, 236, 0]),
+                Err(Error::DrawNotFound)
+            );
+        }
+
+        #[ink::test]
+        fn state_shows_claimable_prizes_of_caller() {
+            let default_accounts = default_accounts();
+            use_random_chain_extension();
+            fund_contract();
+            let mut contract = Lottery::with_config(tier_config());
+
+            set_next_caller(default_accounts.bob);
+            assert_eq!(contract.register_ticket(vec![21, 236, 0], 1), Ok(()));
+            assert_eq!(contract.register_ticket(vec![21, 0, 0], 1), Ok(()));
+            contract.draw();
+
+            let state = contract.get_state();
+            assert_eq!(state.claimable, 1_000_000 + 100_000);
+            assert_eq!(state.unclaimed_prizes, 1_100_000);
+            assert_eq!(state.draw_count, 1);
+            assert_eq!(
+                state.last_result.unwrap().winning_tickets[0].ticket,
+                get_win_ticket()
+            );
+            assert_eq!(contract.claim_prize(0, vec![21, 0, 0]), Ok(100_000));
+            assert_eq!(contract.get_state().claimable, 1_000_000);
+
+            set_next_caller(default_accounts.charlie);
+            assert_eq!(contract.get_state().claimable, 0);
+        }
+
+        #[ink::test]
+        fn ticket_bought_after_draw_cannot_claim_it() {
+            let default_accounts = default_accounts();
+            use_random_chain_extension();
+            fund_contract();
+            let mut contract = Lottery::with_config(tier_config());
+
+            set_next_caller(default_accounts.alice);
+            assert_eq!(contract.register_ticket(vec![21, 0, 0], 1), Ok(()));
+            contract.draw();
+            set_next_caller(default_accounts.bob);
+            assert_eq!(contract.register_ticket(vec![21, 0, 0], 1), Ok(()));
+
+            assert_eq!(contract.claim_prize(0, vec![21, 0, 0]), Err(Error::NoPrize));
+            set_next_caller(default_accounts.alice);
+            assert_eq!(contract.claim_prize(0, vec![21, 0, 0]), Ok(100_000));
+        }
+
+        fn box_config() -> LotteryConfig {
+            LotteryConfig {
+                box_bet: Some(BoxBet {
+                    price: BET_PRICE / 2,
+                    prize: Prize::Share(2_500),
+                }),
+                ..Default::default()
+            }
+        }
+
+        #[ink::test]
+        fn box_bets_disabled_by_default() {
+            let default_accounts = default_accounts();
+            set_next_caller(default_accounts.alice);
+            let mut contract = Lottery::new();
+            assert_eq!(
+                contract.register_box_ticket(get_win_ticket()),
+                Err(Error::BoxBetsDisabled)
+            );
+        }
+
+        #[ink::test]
+        fn box_ticket_needs_box_price() {
+            fund_contract();
+            let default_accounts = default_accounts();
+            set_next_caller(default_accounts.alice);
+            let mut contract = Lottery::with_config(box_config());
+            ink_env::test::set_value_transferred::<Environment>(BET_PRICE / 2 - 1);
+            assert_eq!(
+                contract.register_box_ticket(get_win_ticket()),
+                Err(Error::TicketCosts)
+            );
+            ink_env::test::set_value_transferred::<Environment>(BET_PRICE);
+            assert_eq!(contract.register_box_ticket(get_win_ticket()), Ok(()));
+            assert_eq!(BET_PRICE / 2, contract.get_jackpot());
+        }
+
+        #[ink::test]
+        fn box_tickets_are_stored_sorted() {
+            let default_accounts = default_accounts();
+            let mut contract = Lottery::with_config(box_config());
+            ink_env::test::set_caller::<Environment>(default_accounts.alice);
+            assert_eq!(
+                ink_env::pay_with_call!(contract.register_box_ticket(vec![3, 1, 2]), BET_PRICE / 2),
+                Ok(())
+            );
+            assert_eq!(
+                contract.get_accounts_by_box_ticket(vec![2, 3, 1], 0, 8),
+                vec![default_accounts.alice]
+            );
+            assert!(contract
+                .get_accounts_by_ticket(vec![3, 1, 2], 0, 8)
+                .is_empty());
+            assert_eq!(BET_PRICE / 2, contract.get_jackpot());
+        }
+
+        #[ink::test]
+        fn box_ticket_wins_in_any_order() {
+            let default_accounts = default_accounts();
+            use_random_chain_extension();
+            let mut contract = Lottery::with_config(box_config());
+            set_next_caller(default_accounts.bob);
+            assert_eq!(contract.register_ticket(vec![1, 1, 1], 1), Ok(()));
+            ink_env::test::set_caller::<Environment>(default_accounts.alice);
+            assert_eq!(
+                ink_env::pay_with_call!(
+                    contract.register_box_ticket(vec![123, 21, 236]),
+                    BET_PRICE / 2
+                ),
+                Ok(())
+            );
+            contract.draw();
+
+            let result = contract.get_draw_result(0).unwrap();
+            assert_eq!(result.box_winners, 1);
+            assert_eq!(result.box_prize_per_winner, 375_000);
+            assert_eq!(result.winning_tickets[0].winners, 0);
+            assert_eq!(1_125_000, contract.get_jackpot());
+            assert_eq!(0, contract.get_last_jackpot());
+        }
+
+        fn six_of_forty_nine() -> TicketShape {
+            TicketShape {
+                numbers: 6,
+                min: 1,
+                max: 49,
+                ordered: false,
+                duplicates: false,
+            }
+        }
+
+        /// Replaces the first `count` numbers of `ticket` by numbers which are not drawn.
+        fn with_misses(ticket: &[u8], count: usize) -> Ticket {
+            let mut misses = (1..=49).filter(|number| !ticket.contains(number));
+            let mut picks = ticket.to_vec();
+            for pick in picks.iter_mut().take(count) {
+                *pick = misses.next().unwrap();
+            }
+            picks
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "invalid ticket shape!")]
+        fn too_small_range_without_duplicates_should_panic() {
+            Lottery::with_config(LotteryConfig {
+                ticket: TicketShape {
+                    numbers: 6,
+                    min: 1,
+                    max: 5,
+                    ordered: false,
+                    duplicates: false,
+                },
+                ..Default::default()
+            });
+        }
+
+        #[ink::test]
+        fn invalid_picks_are_rejected() {
+            let default_accounts = default_accounts();
+            set_next_caller(default_accounts.alice);
+            let mut contract = Lottery::with_config(LotteryConfig {
+                ticket: six_of_forty_nine(),
+                ..Default::default()
+            });
+            for ticket in [
+                vec![1, 2, 3, 4, 5],
+                vec![0, 2, 3, 4, 5, 6],
+                vec![1, 2, 3, 4, 5, 50],
+                vec![1, 2, 3, 4, 5, 5],
+            ] {
+                assert_eq!(
+                    contract.register_ticket(ticket, 1),
+                    Err(Error::InvalidTicket)
+                );
+            }
+            assert_eq!(0, contract.get_jackpot());
+        }
+
+        #[ink::test]
+        fn unordered_tickets_are_sorted() {
+            let default_accounts = default_accounts();
+            set_next_caller(default_accounts.alice);
+            let mut contract = Lottery::with_config(LotteryConfig {
+                ticket: six_of_forty_nine(),
+                ..Default::default()
+            });
+            assert_eq!(
+                contract.register_ticket(vec![49, 3, 17, 1, 8, 2], 1),
+                Ok(())
+            );
+            assert_eq!(
+                contract.get_accounts_by_ticket(vec![1, 2, 3, 8, 17, 49], 0, 8),
+                vec![default_accounts.alice]
+            );
+        }
+
+        #[ink::test]
+        fn draw_produces_valid_combination() {
+            use_random_chain_extension();
+            let mut contract = Lottery::with_config(LotteryConfig {
+                ticket: six_of_forty_nine(),
+                ..Default::default()
+            });
+            contract.draw();
+            let winner_ticket = contract.get_winner_ticket();
+            assert_eq!(winner_ticket.len(), 6);
+            assert!(winner_ticket.windows(2).all(|pair| pair[0] < pair[1]));
+            assert!(winner_ticket.iter().all(|number| (1..=49).contains(number)));
+            assert_eq!(
+                winner_ticket,
+                draw_winner_tickets(&contract.get_config(), get_win_ticket_chain_extension())[0]
+            );
+        }
+
+        #[ink::test]
+        fn unordered_tiers_match_in_any_order() {
+            let default_accounts = default_accounts();
+            use_random_chain_extension();
+            let mut contract = Lottery::with_config(LotteryConfig {
+                ticket: six_of_forty_nine(),
+                prize_tiers: vec![PrizeTier {
+                    matches: 4,
+                    bonus: false,
+                    prize: Prize::Fixed(100_000),
+                }],
+                ..Default::default()
+            });
+            let winner_ticket =
+                draw_winner_tickets(&contract.get_config(), get_win_ticket_chain_extension())
+                    .remove(0);
+            let mut four_right = with_misses(&winner_ticket, 2);
+            four_right.reverse();
+
+            set_next_caller(default_accounts.alice);
+            assert_eq!(contract.register_ticket(four_right, 1), Ok(()));
+            set_next_caller(default_accounts.bob);
+            assert_eq!(
+                contract.register_ticket(with_misses(&winner_ticket, 3), 1),
+                Ok(())
+            );
+            contract.draw();
+
+            let result = contract.get_draw_result(0).unwrap();
+            assert_eq!(result.winning_tickets[0].ticket, winner_ticket);
+            assert_eq!(
+                result.tiers,
+                vec![TierResult {
+                    matches: 4,
+                    bonus: false,
+                    winners: 1,
+                    prize_per_winner: 100_000,
+                }]
+            );
+        }
+
+        fn bonus_config() -> LotteryConfig {
+            LotteryConfig {
+                bonus: Some(BonusBall { min: 1, max: 10 }),
+                prize_tiers: vec![
+                    PrizeTier {
+                        matches: 3,
+                        bonus: false,
+                        prize: Prize::Fixed(100_000),
+                    },
+                    PrizeTier {
+                        matches: 2,
+                        bonus: true,
+                        prize: Prize::Share(1_000),
+                    },
+                ],
+                ..Default::default()
+            }
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "invalid prize tier!")]
+        fn bonus_tier_without_bonus_ball_should_panic() {
+            Lottery::with_config(LotteryConfig {
+                prize_tiers: vec![PrizeTier {
+                    matches: 2,
+                    bonus: true,
+                    prize: Prize::Share(1_000),
+                }],
+                ..Default::default()
+            });
+        }
+
+        #[ink::test]
+        fn bonus_number_has_to_be_picked() {
+            let default_accounts = default_accounts();
+            set_next_caller(default_accounts.alice);
+            let mut contract = Lottery::with_config(bonus_config());
+            assert_eq!(
+                contract.register_ticket(get_win_ticket(), 1),
+                Err(Error::InvalidTicket)
+            );
+            assert_eq!(
+                contract.register_ticket(vec![21, 236, 123, 11], 1),
+                Err(Error::InvalidTicket)
+            );
+            assert_eq!(contract.register_ticket(vec![21, 236, 123, 10], 1), Ok(()));
+        }
+
+        #[ink::test]
+        fn bonus_number_is_drawn_after_main_numbers() {
+            use_random_chain_extension();
+            let mut contract = Lottery::with_config(bonus_config());
+            contract.draw();
+            assert_eq!(contract.get_winner_ticket(), vec![21, 236, 123, 1]);
+            assert_eq!(
+                contract.get_draw_result(0).unwrap().winning_tickets[0].ticket,
+                vec![21, 236, 123, 1]
+            );
+        }
+
+        #[ink::test]
+        fn jackpot_needs_bonus_number() {
+            let default_accounts = default_accounts();
+            use_random_chain_extension();
+            let mut contract = Lottery::with_config(bonus_config());
+            set_next_caller(default_accounts.alice);
+            assert_eq!(contract.register_ticket(vec![21, 236, 123, 1], 1), Ok(()));
+            set_next_caller(default_accounts.bob);
+            assert_eq!(contract.register_ticket(vec![21, 236, 123, 2], 1), Ok(()));
+            contract.draw();
+
+            let result = contract.get_draw_result(0).unwrap();
+            assert_eq!(result.winning_tickets[0].winners, 1);
+            assert_eq!(
+                result.tiers,
+                vec![
+                    TierResult {
+                        matches: 3,
+                        bonus: false,
+                        winners: 1,
+                        prize_per_winner: 100_000,
+                    },
+                    TierResult {
+                        matches: 2,
+                        bonus: true,
+                        winners: 0,
+                        prize_per_winner: 0,
+                    },
+                ]
+            );
+            assert_eq!(
+                vec![default_accounts.alice],
+                contract.get_last_winners(0, 8)
+            );
+            assert_eq!(1_900_000, contract.get_last_jackpot());
         }
     }
 }